@@ -1,9 +1,26 @@
-use crate::{renderer::Renderer, scene::Scene, window::WindowHandler};
+use crate::{
+    config::{CityGrowConfig, changed_fields},
+    instance_guard::{self, InstanceGuard},
+    renderer::{Renderer, error::RendererError, swap_chain::SwapChainConfig},
+    scene::{MAX_QUALITY_LEVEL, Scene},
+    window::WindowHandler,
+};
 use anyhow::Result;
-use std::time::Instant;
-use tracing::{debug, error, info};
-use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::WindowsAndMessaging::{KillTimer, SetTimer};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info, warn};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow,
+};
+use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyState, VK_CONTROL};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GA_PARENT, GWL_STYLE, GetAncestor, GetClientRect, GetDesktopWindow, GetSystemMetrics,
+    GetWindowLongPtrW, IsWindow, KillTimer, PostMessageW, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+    SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SWP_NOACTIVATE, SWP_NOZORDER, SetTimer, SetWindowPos,
+    WM_CLOSE, WS_POPUP,
+};
 
 /// Application state that manages the renderer and scene
 pub struct App<S: Scene> {
@@ -13,9 +30,156 @@ pub struct App<S: Scene> {
     frame_count: u32,
     timer_active: bool,
     window_shown: bool,
+    step_mode: bool,
+    paused: bool,
+    /// DPI last reported to the scene, 0 before the first renderer exists
+    dpi: u32,
+    /// Directory Ctrl+S captures are saved to (None = captures off)
+    capture_dir: Option<PathBuf>,
+    /// File the scene state is saved to on exit (None = not saved)
+    state_path: Option<PathBuf>,
+    /// Monitor still to be looked up on the first resize, see `with_monitor_from_window`
+    monitor_claim: Option<MonitorClaim>,
+    /// Keeps other instances off the monitor looked up from the window
+    instance_guard: Option<InstanceGuard>,
+    vsync: bool,
+    /// The fullscreen window covers the whole virtual desktop instead of the primary screen
+    span_monitors: bool,
+    swap_chain_config: SwapChainConfig,
+    quality: AdaptiveQuality,
+    /// Configuration file reloaded when it changes (None = not watched)
+    config_watch: Option<ConfigWatch>,
+    /// Window the host (e.g. Lively) embedded ours into, None while top-level
+    host: Option<HWND>,
+    host_ticks: u32,
+    framerate: u32,
+    /// Frame rate on battery power (0 = same as `framerate`)
+    battery_framerate: u32,
+    /// Interval the frame timer currently runs at, in milliseconds
+    timer_interval: u32,
+    power_ticks: u32,
+}
+
+/// Timer ticks between two checks whether the host window still exists
+const HOST_POLL_TICKS: u32 = 60;
+/// Timer ticks between two checks whether the computer runs on battery
+const POWER_POLL_TICKS: u32 = 120;
+
+/// Time between two checks of the configuration file's modification time. The timer keeps
+/// firing at least this often while the scene is idle, so edits are still picked up.
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Configuration file polled for changes, so settings can be tuned without a restart
+struct ConfigWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    /// Last configuration handed to the scene
+    config: CityGrowConfig,
+    last_poll: Instant,
+}
+
+impl ConfigWatch {
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// The reloaded configuration if the file changed since the last poll.
+    /// Checks the file only every `CONFIG_POLL_INTERVAL`, whatever the frame rate.
+    fn poll(&mut self) -> Option<CityGrowConfig> {
+        if self.last_poll.elapsed() < CONFIG_POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = Self::modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        // Remember the time even if the file is broken, it is retried once it is saved again
+        self.modified = modified;
+        match CityGrowConfig::from_path(&self.path) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                warn!("Ignoring changed config file: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Monitor identity taken from where the host placed the window
+struct MonitorClaim {
+    /// Directory of the per-monitor state file (None = state not saved)
+    state_dir: Option<PathBuf>,
+}
+
+/// Number of frames averaged before the quality level is reconsidered
+const QUALITY_WINDOW: u32 = 120;
+/// Average frame time, relative to the budget, above which quality is lowered
+const QUALITY_LOWER_RATIO: f32 = 1.5;
+/// Average frame time, relative to the budget, below which quality is raised again
+const QUALITY_RAISE_RATIO: f32 = 1.1;
+
+/// Lowers the scene quality while frames consistently miss the frame budget
+/// and raises it again once there is headroom
+struct AdaptiveQuality {
+    level: u8,
+    min_level: u8,
+    max_level: u8,
+    frame_budget: f32,
+    delta_sum: f32,
+    frames: u32,
+}
+
+impl AdaptiveQuality {
+    fn disabled() -> Self {
+        Self {
+            level: MAX_QUALITY_LEVEL,
+            min_level: MAX_QUALITY_LEVEL,
+            max_level: MAX_QUALITY_LEVEL,
+            frame_budget: 0.0,
+            delta_sum: 0.0,
+            frames: 0,
+        }
+    }
+
+    /// Record a frame time, returns the new level if it changed
+    fn record(&mut self, delta: f32) -> Option<u8> {
+        if self.min_level >= self.max_level {
+            return None;
+        }
+        self.delta_sum += delta;
+        self.frames += 1;
+        if self.frames < QUALITY_WINDOW {
+            return None;
+        }
+
+        let average = self.delta_sum / self.frames as f32;
+        self.delta_sum = 0.0;
+        self.frames = 0;
+
+        let level = if average > self.frame_budget * QUALITY_LOWER_RATIO {
+            self.level.saturating_sub(1).max(self.min_level)
+        } else if average < self.frame_budget * QUALITY_RAISE_RATIO {
+            (self.level + 1).min(self.max_level)
+        } else {
+            self.level
+        };
+        if level == self.level {
+            return None;
+        }
+        self.level = level;
+        Some(level)
+    }
 }
 
 const TIMER_ID: usize = 1;
+/// Virtual key that saves the current frame together with Ctrl
+const CAPTURE_KEY: u32 = b'S' as u32;
+/// Simulated frame time used when stepping manually
+const STEP_DELTA: f32 = 1.0 / 60.0;
 
 impl<S: Scene> App<S> {
     pub fn new(scene: S) -> Self {
@@ -26,7 +190,261 @@ impl<S: Scene> App<S> {
             frame_count: 0,
             timer_active: true,
             window_shown: false,
+            step_mode: false,
+            paused: false,
+            dpi: 0,
+            capture_dir: None,
+            state_path: None,
+            monitor_claim: None,
+            instance_guard: None,
+            vsync: true,
+            span_monitors: false,
+            swap_chain_config: SwapChainConfig::default(),
+            quality: AdaptiveQuality::disabled(),
+            config_watch: None,
+            host: None,
+            host_ticks: 0,
+            framerate: 60,
+            battery_framerate: 0,
+            timer_interval: 16,
+            power_ticks: 0,
+        }
+    }
+
+    /// Frame rate of the timer, lowered to `battery_framerate` while on battery power
+    /// (0 = no change on battery). Has to match the window's initial timer.
+    pub fn with_framerate(mut self, framerate: u32, battery_framerate: u32) -> Self {
+        self.framerate = framerate.max(1);
+        self.battery_framerate = battery_framerate;
+        self.timer_interval = 1000 / self.framerate;
+        self
+    }
+
+    /// Switch the timer between the normal and the battery frame rate when the power source
+    /// changed. Checks only every `POWER_POLL_TICKS` calls.
+    fn check_power_source(&mut self, hwnd: HWND) {
+        self.power_ticks += 1;
+        if self.power_ticks < POWER_POLL_TICKS || self.battery_framerate == 0 {
+            return;
+        }
+        self.power_ticks = 0;
+
+        let mut status = SYSTEM_POWER_STATUS::default();
+        // Unknown (255) counts as AC power
+        let on_battery =
+            unsafe { GetSystemPowerStatus(&mut status) }.is_ok() && status.ACLineStatus == 0;
+        let framerate = if on_battery {
+            self.battery_framerate
+        } else {
+            self.framerate
+        };
+        let interval = 1000 / framerate;
+        if interval == self.timer_interval {
+            return;
+        }
+        info!(
+            framerate,
+            on_battery, "Power source changed, adjusting the frame rate"
+        );
+        self.timer_interval = interval;
+        self.quality.frame_budget = 1.0 / framerate as f32;
+        if self.timer_active {
+            // Same id replaces the running timer
+            unsafe {
+                SetTimer(Some(hwnd), TIMER_ID, interval, None);
+            }
+        }
+    }
+
+    /// Reload `path` while running whenever it changes and pass it to the scene.
+    /// `config` is the configuration the app was started with.
+    pub fn with_config_watch(mut self, path: PathBuf, config: CityGrowConfig) -> Self {
+        self.config_watch = Some(ConfigWatch {
+            modified: ConfigWatch::modified_time(&path),
+            path,
+            config,
+            last_poll: Instant::now(),
+        });
+        self
+    }
+
+    /// Hand a changed configuration file to the scene
+    fn reload_config(&mut self) {
+        let Some(watch) = &mut self.config_watch else {
+            return;
+        };
+        let Some(config) = watch.poll() else {
+            return;
+        };
+        let app_changes = changed_fields(&watch.config.app, &config.app);
+        if !app_changes.is_empty() {
+            info!(
+                "Config reloaded, app settings take effect after a restart: {}",
+                app_changes.join(", ")
+            );
+        }
+        self.scene.apply_config(&config);
+        watch.config = config;
+    }
+
+    /// Follow the window we are embedded into. Returns false if the host went away without
+    /// giving us a new one, the window is closed then instead of rendering into nothing.
+    /// Checks only every `HOST_POLL_TICKS` calls.
+    fn check_host(&mut self, hwnd: HWND) -> bool {
+        self.host_ticks += 1;
+        if self.host_ticks < HOST_POLL_TICKS {
+            return true;
+        }
+        self.host_ticks = 0;
+
+        let parent = unsafe {
+            let parent = GetAncestor(hwnd, GA_PARENT);
+            (!parent.is_invalid() && parent != GetDesktopWindow()).then_some(parent)
+        };
+        match (self.host, parent) {
+            (Some(host), _) if !unsafe { IsWindow(Some(host)) }.as_bool() => match parent {
+                Some(parent) if parent != host => {
+                    info!("Host window was replaced, following the new one");
+                    self.host = Some(parent);
+                }
+                _ => {
+                    warn!("Host window is gone, closing");
+                    unsafe {
+                        let _ = PostMessageW(Some(hwnd), WM_CLOSE, WPARAM(0), LPARAM(0));
+                    }
+                    return false;
+                }
+            },
+            (host, Some(parent)) if host != Some(parent) => {
+                debug!("Embedded into host window {:?}", parent);
+                self.host = Some(parent);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Adapt the scene quality level between `min_level` and `max_level` to keep
+    /// frames within the budget of `framerate`
+    pub fn with_adaptive_quality(mut self, framerate: u32, min_level: u8, max_level: u8) -> Self {
+        let max_level = max_level.min(MAX_QUALITY_LEVEL);
+        let min_level = min_level.min(max_level);
+        self.quality = AdaptiveQuality {
+            level: max_level,
+            min_level,
+            max_level,
+            frame_budget: 1.0 / framerate.max(1) as f32,
+            ..AdaptiveQuality::disabled()
+        };
+        self.scene.set_quality_level(max_level);
+        self
+    }
+
+    /// Swap chain settings used whenever the renderer is (re)created
+    pub fn with_swap_chain_config(mut self, config: SwapChainConfig) -> Self {
+        self.swap_chain_config = config;
+        self
+    }
+
+    /// Save the scene state to `path` when the window is destroyed (None = don't save)
+    pub fn with_state_path(mut self, path: Option<PathBuf>) -> Self {
+        self.state_path = path;
+        self
+    }
+
+    /// Take the single-instance guard and the state file in `state_dir` (None = not saved)
+    /// of the monitor the window is on at the first resize, for when no monitor was given
+    /// on the command line
+    pub fn with_monitor_from_window(mut self, enabled: bool, state_dir: Option<PathBuf>) -> Self {
+        self.monitor_claim = enabled.then_some(MonitorClaim { state_dir });
+        self
+    }
+
+    /// Look up the monitor the host placed the window on, take its instance guard and continue
+    /// its saved city. Returns false if another instance still renders there, the window is
+    /// closed then. Without a monitor there is nothing to tell instances apart by, they run
+    /// unguarded and don't save their state instead of sharing one.
+    fn claim_monitor(&mut self, hwnd: HWND) -> bool {
+        let Some(claim) = self.monitor_claim.take() else {
+            return true;
+        };
+        let Some(monitor) = instance_guard::monitor_id(hwnd) else {
+            warn!("Window is on no monitor, running without single-instance guard and saved state");
+            return true;
+        };
+        match InstanceGuard::acquire(&monitor) {
+            Ok(Some(guard)) => self.instance_guard = Some(guard),
+            Ok(None) => {
+                warn!(
+                    "Another instance is still running on monitor {}, closing",
+                    monitor
+                );
+                unsafe {
+                    let _ = PostMessageW(Some(hwnd), WM_CLOSE, WPARAM(0), LPARAM(0));
+                }
+                return false;
+            }
+            Err(e) => warn!("Running without single-instance guard: {:?}", e),
+        }
+
+        if let Some(dir) = claim.state_dir {
+            let path = instance_guard::state_path(&dir, &monitor);
+            if path.exists()
+                && let Err(e) = self.scene.restore_state(&path)
+            {
+                warn!("Starting a new city: {:?}", e);
+            }
+            self.state_path = Some(path);
+        }
+        true
+    }
+
+    /// Wait for the display refresh when presenting (off = uncapped frames, which also stops
+    /// coalescing renders that come in close together)
+    pub fn with_vsync(mut self, enabled: bool) -> Self {
+        self.vsync = enabled;
+        self
+    }
+
+    /// Follow the virtual desktop on display changes, has to match the window's initial size
+    pub fn with_span_monitors(mut self, enabled: bool) -> Self {
+        self.span_monitors = enabled;
+        self
+    }
+
+    /// Advance the animation only when `step_once` is called (one frame per keypress)
+    pub fn with_step_mode(mut self, enabled: bool) -> Self {
+        self.step_mode = enabled;
+        self
+    }
+
+    /// Save the current frame as a PNG into `dir` when Ctrl+S is pressed
+    pub fn with_capture_dir(mut self, dir: PathBuf) -> Self {
+        self.capture_dir = Some(dir);
+        self
+    }
+
+    /// Save the current frame into the capture directory, named after the current time
+    fn capture_frame(&self) {
+        let (Some(renderer), Some(dir)) = (&self.renderer, &self.capture_dir) else {
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let path = dir.join(format!("city_grow_{}.png", timestamp));
+        if let Err(e) = renderer.save_png(&path) {
+            error!("Failed to save frame: {:?}", e);
+        }
+    }
+
+    /// Render exactly one frame with a fixed frame time and log the scene state
+    pub fn step_once(&mut self) {
+        if let Err(e) = self.render_frame_with_delta(STEP_DELTA) {
+            error!("Render error: {:?}", e);
+            return;
         }
+        info!("Step {}: {}", self.frame_count, self.scene.status());
     }
 
     fn ensure_initialized(&mut self, hwnd: HWND, width: u32, height: u32) -> bool {
@@ -34,14 +452,24 @@ impl<S: Scene> App<S> {
             return true;
         }
 
-        match Renderer::new(hwnd, width, height, true) {
-            // Enable vsync for smooth wallpaper rendering
+        match Renderer::new(hwnd, width, height, self.vsync, &self.swap_chain_config) {
             Ok(renderer) => {
                 debug!(
                     "Renderer initialized successfully with size {}x{}",
                     width, height
                 );
+                let dpi = renderer.dpi();
                 self.renderer = Some(renderer);
+                // A DPI is only known once a renderer existed, this one replaces it
+                let replaced = self.dpi != 0;
+                if dpi != self.dpi {
+                    debug!(dpi, "Display DPI changed");
+                    self.dpi = dpi;
+                    self.scene.on_dpi_changed(dpi);
+                }
+                if replaced {
+                    self.scene.on_renderer_recreated(width, height);
+                }
                 true
             }
             Err(e) => {
@@ -51,17 +479,72 @@ impl<S: Scene> App<S> {
         }
     }
 
-    fn render_frame(&mut self) -> Result<()> {
-        let renderer = self
-            .renderer
-            .as_mut()
-            .ok_or_else(|| anyhow::anyhow!("Renderer not initialized"))?;
+    /// Render a frame unless one was already rendered within half the timer interval,
+    /// so regular timer ticks are never dropped
+    ///
+    /// Paint storms (dragging, exposing) and timer ticks can arrive back to back,
+    /// rendering twice in that case only wastes GPU time.
+    fn render_frame_coalesced(&mut self, hwnd: HWND) {
+        let min_interval = Duration::from_millis(u64::from(self.timer_interval / 2));
+        if self.vsync && self.last_frame_time.elapsed() < min_interval {
+            return;
+        }
+        if let Err(e) = self.render_frame(hwnd) {
+            error!("Render error: {:?}", e);
+        }
+    }
+
+    fn render_frame(&mut self, hwnd: HWND) -> Result<()> {
+        // With frame pacing this lines the frame up with the display, measure the delta after
+        if let Some(renderer) = &self.renderer {
+            renderer.wait_for_frame();
+        }
 
         // Calculate delta time
         let now = Instant::now();
         let delta = now.duration_since(self.last_frame_time).as_secs_f32();
         self.last_frame_time = now;
 
+        if let Some(level) = self.quality.record(delta) {
+            info!("Frame time budget: switching to quality level {}", level);
+            self.scene.set_quality_level(level);
+        }
+
+        let result = self.render_frame_with_delta(delta);
+        if let Err(e) = &result
+            && let Some(lost @ RendererError::DeviceLost { .. }) = e.downcast_ref()
+        {
+            error!("{}, recreating the renderer", lost);
+            self.recreate_renderer(hwnd);
+            return Ok(());
+        }
+        result
+    }
+
+    /// Replace the renderer with a fresh one and let the scene start over on it
+    ///
+    /// If recreating fails the renderer stays uninitialized, the next timer tick or paint
+    /// retries through `ensure_initialized`.
+    fn recreate_renderer(&mut self, hwnd: HWND) {
+        let Some(renderer) = self.renderer.take() else {
+            return;
+        };
+        match renderer.recreate(hwnd) {
+            Ok(renderer) => {
+                let (width, height) = renderer.size();
+                self.renderer = Some(renderer);
+                self.scene.on_renderer_recreated(width, height);
+            }
+            Err(e) => error!("Failed to recreate renderer: {:?}", e),
+        }
+    }
+
+    fn render_frame_with_delta(&mut self, delta: f32) -> Result<()> {
+        let renderer = self
+            .renderer
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Renderer not initialized"))?;
+
         // Prepare renderer (must be before begin_draw)
         self.scene.prepare_render(renderer)?;
 
@@ -70,6 +553,11 @@ impl<S: Scene> App<S> {
         self.scene.render(renderer, delta)?;
         renderer.end_draw()?;
 
+        // Structured event hosts can pick up from the log, e.g. to swap the config between cycles
+        for phase in self.scene.take_phase_changes() {
+            info!(target: "city_grow::phase", ?phase, "Animation phase changed");
+        }
+
         self.frame_count += 1;
         if self.frame_count.is_multiple_of(60) {
             debug!("Rendered {} frames", self.frame_count);
@@ -82,7 +570,8 @@ impl<S: Scene> WindowHandler for App<S> {
     fn on_paint(&mut self, hwnd: HWND) {
         // During active animation, timer handles all rendering
         // Return immediately to avoid any redundant work
-        if self.timer_active {
+        // In step mode frames only advance on keypress
+        if self.timer_active || self.step_mode {
             return;
         }
 
@@ -106,9 +595,7 @@ impl<S: Scene> WindowHandler for App<S> {
         }
 
         // Render the current frame
-        if let Err(e) = self.render_frame() {
-            error!("Render error: {:?}", e);
-        }
+        self.render_frame_coalesced(hwnd);
     }
 
     fn on_timer(&mut self, hwnd: HWND) {
@@ -130,33 +617,93 @@ impl<S: Scene> WindowHandler for App<S> {
             return;
         }
 
+        // In step mode the timer is only used to initialize the renderer
+        if self.step_mode {
+            unsafe {
+                let _ = KillTimer(Some(hwnd), TIMER_ID);
+            }
+            self.timer_active = false;
+            info!("Step mode: press any key to advance one frame");
+            return;
+        }
+
+        if !self.check_host(hwnd) {
+            return;
+        }
+        self.reload_config();
+        self.check_power_source(hwnd);
+
+        // Keep the timer running while paused, so resuming needs no restart
+        if self.paused {
+            return;
+        }
+
+        if self.scene.is_finished() {
+            info!("Scene finished, closing");
+            unsafe {
+                let _ = KillTimer(Some(hwnd), TIMER_ID);
+                let _ = PostMessageW(Some(hwnd), WM_CLOSE, WPARAM(0), LPARAM(0));
+            }
+            self.timer_active = false;
+            return;
+        }
+
         // If scene started animating again but timer was stopped, restart it
         if !self.timer_active && self.scene.is_animating() {
             unsafe {
-                SetTimer(Some(hwnd), TIMER_ID, 16, None);
+                SetTimer(Some(hwnd), TIMER_ID, self.timer_interval, None);
             }
             self.timer_active = true;
+            // The idle time is no frame, it would skew the frame time of adaptive quality
+            self.last_frame_time = Instant::now();
             debug!("Animation resumed, timer restarted");
         }
 
         // Check if scene is still animating
         if self.scene.is_animating() {
-            if let Err(e) = self.render_frame() {
-                error!("Render error: {:?}", e);
+            self.render_frame_coalesced(hwnd);
+        } else if let Some(wait) = self.scene.wake_after() {
+            // Same id replaces the frame timer with one firing when the scene wakes up again,
+            // re-armed with the rest of the wait if it fires early (e.g. to poll the config)
+            let wait = match &self.config_watch {
+                Some(_) => wait.min(CONFIG_POLL_INTERVAL),
+                None => wait,
+            };
+            unsafe {
+                SetTimer(Some(hwnd), TIMER_ID, (wait.as_millis() as u32).max(1), None);
+            }
+            if self.timer_active {
+                debug!("Scene idle for {:?}, timer slowed down", wait);
             }
+            self.timer_active = false;
         } else if self.timer_active {
-            // Animation complete, stop timer
+            // Animation complete, stop the frame timer. A watched config file keeps a slow
+            // timer running, an edit may start the animation again.
             unsafe {
-                let _ = KillTimer(Some(hwnd), TIMER_ID);
+                if self.config_watch.is_some() {
+                    SetTimer(
+                        Some(hwnd),
+                        TIMER_ID,
+                        CONFIG_POLL_INTERVAL.as_millis() as u32,
+                        None,
+                    );
+                } else {
+                    let _ = KillTimer(Some(hwnd), TIMER_ID);
+                }
             }
             self.timer_active = false;
-            info!("Animation complete, timer stopped - entering idle state");
+            info!("Animation complete, frame timer stopped - entering idle state");
         }
     }
 
     fn on_resize(&mut self, hwnd: HWND, width: u32, height: u32) {
         debug!(width, height, "Window resized");
 
+        // The first resize comes once the host placed the window on its monitor
+        if !self.claim_monitor(hwnd) {
+            return;
+        }
+
         // Show window on first resize (after Lively has positioned it)
         if !self.window_shown {
             unsafe {
@@ -167,8 +714,24 @@ impl<S: Scene> WindowHandler for App<S> {
             debug!("Window shown after initial resize");
         }
 
-        // Recreate renderer with new size
-        self.renderer = None;
+        // Lively sends WM_SIZE with unchanged dimensions on focus changes,
+        // those must not restart the city
+        if self
+            .renderer
+            .as_ref()
+            .is_some_and(|renderer| renderer.size() == (width, height))
+        {
+            debug!("Size unchanged, ignoring resize");
+            return;
+        }
+
+        // Resize the renderer in place, only rebuild it if that fails
+        if let Some(renderer) = &mut self.renderer
+            && let Err(e) = renderer.resize(width, height)
+        {
+            warn!("Failed to resize renderer, recreating it: {:?}", e);
+            self.renderer = None;
+        }
 
         // Notify scene
         self.scene.on_resize(width, height);
@@ -177,7 +740,117 @@ impl<S: Scene> WindowHandler for App<S> {
         self.ensure_initialized(hwnd, width, height);
     }
 
+    fn on_dpi_changed(&mut self, _hwnd: HWND, dpi: u32) {
+        if dpi == self.dpi {
+            return;
+        }
+        // Rebuilding the renderer would wipe the city, and it draws in physical pixels anyway.
+        // Only a scene that scales with the DPI starts over.
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+        debug!(dpi, "Display DPI changed");
+        renderer.set_dpi(dpi);
+        self.dpi = dpi;
+        self.scene.on_dpi_changed(dpi);
+    }
+
+    fn on_display_change(&mut self, hwnd: HWND) {
+        // Hosts like Lively don't always send WM_SIZE after a resolution change, fullscreen
+        // (popup) windows follow the monitor they are on or, spanning monitors, the virtual
+        // desktop. Others keep their client area
+        let (width, height) = unsafe {
+            let style = GetWindowLongPtrW(hwnd, GWL_STYLE) as u32;
+            let mut rect = windows::Win32::Foundation::RECT::default();
+            if style & WS_POPUP.0 != 0 && self.span_monitors {
+                // Monitors may have been added, removed or moved, cover them all again
+                let (x, y, width, height) = (
+                    GetSystemMetrics(SM_XVIRTUALSCREEN),
+                    GetSystemMetrics(SM_YVIRTUALSCREEN),
+                    GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                    GetSystemMetrics(SM_CYVIRTUALSCREEN),
+                );
+                let _ = SetWindowPos(
+                    hwnd,
+                    None,
+                    x,
+                    y,
+                    width,
+                    height,
+                    SWP_NOZORDER | SWP_NOACTIVATE,
+                );
+                (width, height)
+            } else if style & WS_POPUP.0 != 0 {
+                // SM_CXSCREEN/SM_CYSCREEN would be the primary monitor, instances on other
+                // monitors with another resolution would get a renderer of the wrong size
+                let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+                let mut info = MONITORINFO {
+                    cbSize: size_of::<MONITORINFO>() as u32,
+                    ..Default::default()
+                };
+                if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+                    return;
+                }
+                let monitor_rect = info.rcMonitor;
+                (
+                    monitor_rect.right - monitor_rect.left,
+                    monitor_rect.bottom - monitor_rect.top,
+                )
+            } else if GetClientRect(hwnd, &mut rect).is_ok() {
+                (rect.right - rect.left, rect.bottom - rect.top)
+            } else {
+                return;
+            }
+        };
+        let (width, height) = (width.max(1) as u32, height.max(1) as u32);
+        info!(width, height, "Display changed, rebuilding renderer");
+
+        // A new monitor may be driven by another adapter, start over on a fresh device
+        self.renderer = None;
+        if !self.ensure_initialized(hwnd, width, height) {
+            self.scene.on_resize(width, height);
+        }
+    }
+
+    fn on_key(&mut self, _hwnd: HWND, virtual_key: u32) {
+        // High bit of the key state is set while the key is held down
+        let control_down = unsafe { GetKeyState(VK_CONTROL.0 as i32) } < 0;
+        if control_down && virtual_key == CAPTURE_KEY {
+            self.capture_frame();
+            return;
+        }
+
+        if self.step_mode && self.renderer.is_some() {
+            self.step_once();
+        }
+    }
+
+    fn on_pause(&mut self) {
+        if self.paused {
+            return;
+        }
+        self.paused = true;
+        self.scene.pause();
+        info!("Animation paused");
+    }
+
+    fn on_resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.paused = false;
+        self.scene.resume();
+        // The paused time must not show up as one huge frame
+        self.last_frame_time = Instant::now();
+        info!("Animation resumed");
+    }
+
     fn on_destroy(&mut self) {
         info!("Application shutting down");
+        if let Some(path) = &self.state_path
+            && let Err(e) = self.scene.save_state(path)
+        {
+            error!("Failed to save scene state: {:?}", e);
+        }
     }
 }
@@ -1,18 +1,53 @@
 use crate::{
+    config::{CityGrowConfig, changed_fields},
     ext::color_ext::D2DColorExt,
-    renderer::{Renderer, draw_operation::DrawOperation},
-    scene::Scene,
+    renderer::{
+        Renderer,
+        draw_operation::DrawOperation,
+        offscreen::OffscreenRenderer,
+        svg,
+        text_overlay::{TextAnchor, TextOverlay},
+    },
+    scene::{AnimationPhase, MAX_QUALITY_LEVEL, Scene},
 };
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use bitvec::vec::BitVec;
-use rand::rngs::ThreadRng;
-use rand::{RngExt, seq::IndexedRandom};
+use rand::rngs::StdRng;
+use rand::{
+    RngExt, SeedableRng,
+    seq::{IndexedRandom, IteratorRandom},
+};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use tracing::debug;
-use windows::Win32::Graphics::Direct2D::Common::{D2D_RECT_F, D2D1_COLOR_F};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use windows::Win32::{
+    Graphics::{
+        Direct2D::Common::{D2D_RECT_F, D2D1_COLOR_F},
+        DirectWrite::IDWriteTextFormat,
+    },
+    System::SystemInformation::GetLocalTime,
+};
 use windows_numerics::Vector2;
 
+/// Settings that decide the grid or how a city starts; changing one while running starts a
+/// new city, all others apply to what is drawn next
+const RESTART_FIELDS: &[&str] = &[
+    "scale",
+    "scale_with_dpi",
+    "wrap_edges",
+    "territorial",
+    "allow_diagonal",
+    "curved_roads",
+    "start_branches",
+    "start_placement",
+    "seed",
+];
+
 const POSITIONS: [Pos; 4] = [
     Pos { x: 1, y: 0 },  // East
     Pos { x: -1, y: 0 }, // West
@@ -20,13 +55,28 @@ const POSITIONS: [Pos; 4] = [
     Pos { x: 0, y: -1 }, // North
 ];
 
+/// `POSITIONS` followed by the diagonal steps, for `allow_diagonal`
+const POSITIONS_WITH_DIAGONALS: [Pos; 8] = [
+    Pos { x: 1, y: 0 },   // East
+    Pos { x: -1, y: 0 },  // West
+    Pos { x: 0, y: 1 },   // South
+    Pos { x: 0, y: -1 },  // North
+    Pos { x: 1, y: -1 },  // North-east
+    Pos { x: -1, y: -1 }, // North-west
+    Pos { x: 1, y: 1 },   // South-east
+    Pos { x: -1, y: 1 },  // South-west
+];
+
+#[derive(Debug)]
 enum Event {
     BranchOff {
         child_id: u32,
+        parent_id: u32,
         parent_pos: Pos,
         child_pos: Pos,
         parent_mode: BranchMode,
         child_color: Hsla,
+        child_thickness: f32,
     },
     Move {
         branch_id: u32,
@@ -34,13 +84,421 @@ enum Event {
         to: Pos,
         mode: BranchMode,
         color: Hsla,
+        thickness: f32,
         own_fields_tip: Pos,
     },
 }
 
+/// One drawing command in the newline-delimited JSON export (and in saved scene states)
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExportEntry {
+    Line {
+        branch: u32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        width: f32,
+        color: [f32; 4],
+    },
+    Rect {
+        branch: u32,
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+        /// Corner radius, 0 for sharp corners
+        radius: f32,
+        /// Outline width, None for filled rectangles
+        width: Option<f32>,
+        color: [f32; 4],
+    },
+    Polyline {
+        branch: u32,
+        points: Vec<[f32; 2]>,
+        width: f32,
+        color: [f32; 4],
+    },
+    /// Smoothed path through `points`, see `DrawOperation::BezierPath`
+    BezierPath {
+        branch: u32,
+        points: Vec<[f32; 2]>,
+        width: f32,
+        color: [f32; 4],
+    },
+    GradientRect {
+        branch: u32,
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+        /// Gradient line, in pixels like the rectangle
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        /// (position along the gradient line in 0..1, color) pairs
+        stops: Vec<(f32, [f32; 4])>,
+    },
+    Ellipse {
+        branch: u32,
+        cx: f32,
+        cy: f32,
+        rx: f32,
+        ry: f32,
+        /// Outline width, None for filled ellipses
+        width: Option<f32>,
+        color: [f32; 4],
+    },
+    /// Circular arc, angles in degrees clockwise from the positive X axis
+    Arc {
+        branch: u32,
+        cx: f32,
+        cy: f32,
+        radius: f32,
+        start_angle: f32,
+        sweep_angle: f32,
+        width: f32,
+        color: [f32; 4],
+    },
+    Text {
+        branch: u32,
+        text: String,
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+        color: [f32; 4],
+    },
+}
+
+impl ExportEntry {
+    fn new(branch: u32, operation: &DrawOperation) -> Self {
+        let rgba = |color: &D2D1_COLOR_F| [color.r, color.g, color.b, color.a];
+        match operation {
+            DrawOperation::Line {
+                start,
+                end,
+                color,
+                thickness,
+            } => Self::Line {
+                branch,
+                x1: start.X,
+                y1: start.Y,
+                x2: end.X,
+                y2: end.Y,
+                width: *thickness,
+                color: rgba(color),
+            },
+            DrawOperation::Rect {
+                rect,
+                color,
+                thickness,
+            } => Self::Rect {
+                branch,
+                left: rect.left,
+                top: rect.top,
+                right: rect.right,
+                bottom: rect.bottom,
+                radius: 0.0,
+                width: Some(*thickness),
+                color: rgba(color),
+            },
+            DrawOperation::FilledRect { rect, color } => Self::Rect {
+                branch,
+                left: rect.left,
+                top: rect.top,
+                right: rect.right,
+                bottom: rect.bottom,
+                radius: 0.0,
+                width: None,
+                color: rgba(color),
+            },
+            DrawOperation::FilledRoundedRect {
+                rect,
+                radius_x,
+                color,
+                ..
+            } => Self::Rect {
+                branch,
+                left: rect.left,
+                top: rect.top,
+                right: rect.right,
+                bottom: rect.bottom,
+                radius: *radius_x,
+                width: None,
+                color: rgba(color),
+            },
+            DrawOperation::Polyline {
+                points,
+                color,
+                thickness,
+            } => Self::Polyline {
+                branch,
+                points: points.iter().map(|point| [point.X, point.Y]).collect(),
+                width: *thickness,
+                color: rgba(color),
+            },
+            DrawOperation::BezierPath {
+                points,
+                color,
+                thickness,
+            } => Self::BezierPath {
+                branch,
+                points: points.iter().map(|point| [point.X, point.Y]).collect(),
+                width: *thickness,
+                color: rgba(color),
+            },
+            DrawOperation::GradientRect {
+                rect,
+                stops,
+                start,
+                end,
+            } => Self::GradientRect {
+                branch,
+                left: rect.left,
+                top: rect.top,
+                right: rect.right,
+                bottom: rect.bottom,
+                x1: start.X,
+                y1: start.Y,
+                x2: end.X,
+                y2: end.Y,
+                stops: stops
+                    .iter()
+                    .map(|(position, color)| (*position, rgba(color)))
+                    .collect(),
+            },
+            DrawOperation::Ellipse {
+                center,
+                radius_x,
+                radius_y,
+                color,
+                filled,
+                thickness,
+            } => Self::Ellipse {
+                branch,
+                cx: center.X,
+                cy: center.Y,
+                rx: *radius_x,
+                ry: *radius_y,
+                width: (!filled).then_some(*thickness),
+                color: rgba(color),
+            },
+            DrawOperation::Arc {
+                center,
+                radius,
+                start_angle,
+                sweep_angle,
+                color,
+                thickness,
+            } => Self::Arc {
+                branch,
+                cx: center.X,
+                cy: center.Y,
+                radius: *radius,
+                start_angle: *start_angle,
+                sweep_angle: *sweep_angle,
+                width: *thickness,
+                color: rgba(color),
+            },
+            DrawOperation::Text {
+                text, rect, color, ..
+            } => Self::Text {
+                branch,
+                text: text.clone(),
+                left: rect.left,
+                top: rect.top,
+                right: rect.right,
+                bottom: rect.bottom,
+                color: rgba(color),
+            },
+        }
+    }
+
+    /// Branch id the command belongs to
+    fn branch(&self) -> u32 {
+        match self {
+            Self::Line { branch, .. }
+            | Self::Rect { branch, .. }
+            | Self::Polyline { branch, .. }
+            | Self::BezierPath { branch, .. }
+            | Self::GradientRect { branch, .. }
+            | Self::Ellipse { branch, .. }
+            | Self::Arc { branch, .. }
+            | Self::Text { branch, .. } => *branch,
+        }
+    }
+
+    /// Draw operation the command was created from, None for text (the text format isn't
+    /// exported)
+    fn to_operation(&self) -> Option<DrawOperation> {
+        let color = |[r, g, b, a]: [f32; 4]| D2D1_COLOR_F { r, g, b, a };
+        let point = |[x, y]: [f32; 2]| Vector2 { X: x, Y: y };
+        let operation = match self {
+            Self::Line {
+                x1,
+                y1,
+                x2,
+                y2,
+                width,
+                color: rgba,
+                ..
+            } => DrawOperation::line(point([*x1, *y1]), point([*x2, *y2]), color(*rgba), *width),
+            Self::Rect {
+                left,
+                top,
+                right,
+                bottom,
+                radius,
+                width,
+                color: rgba,
+                ..
+            } => {
+                let rect = D2D_RECT_F {
+                    left: *left,
+                    top: *top,
+                    right: *right,
+                    bottom: *bottom,
+                };
+                match width {
+                    Some(width) => DrawOperation::rect(rect, color(*rgba), *width),
+                    None if *radius > 0.0 => {
+                        DrawOperation::filled_rounded_rect(rect, *radius, *radius, color(*rgba))
+                    }
+                    None => DrawOperation::filled_rect(rect, color(*rgba)),
+                }
+            }
+            Self::Polyline {
+                points,
+                width,
+                color: rgba,
+                ..
+            } => DrawOperation::Polyline {
+                points: points.iter().copied().map(point).collect(),
+                color: color(*rgba),
+                thickness: *width,
+            },
+            Self::BezierPath {
+                points,
+                width,
+                color: rgba,
+                ..
+            } => DrawOperation::bezier_path(
+                points.iter().copied().map(point).collect(),
+                color(*rgba),
+                *width,
+            ),
+            Self::GradientRect {
+                left,
+                top,
+                right,
+                bottom,
+                x1,
+                y1,
+                x2,
+                y2,
+                stops,
+                ..
+            } => DrawOperation::gradient_rect(
+                D2D_RECT_F {
+                    left: *left,
+                    top: *top,
+                    right: *right,
+                    bottom: *bottom,
+                },
+                stops
+                    .iter()
+                    .map(|(position, rgba)| (*position, color(*rgba)))
+                    .collect(),
+                point([*x1, *y1]),
+                point([*x2, *y2]),
+            ),
+            Self::Ellipse {
+                cx,
+                cy,
+                rx,
+                ry,
+                width,
+                color: rgba,
+                ..
+            } => match width {
+                Some(width) => {
+                    DrawOperation::ellipse(point([*cx, *cy]), *rx, *ry, color(*rgba), *width)
+                }
+                None => DrawOperation::filled_ellipse(point([*cx, *cy]), *rx, *ry, color(*rgba)),
+            },
+            Self::Arc {
+                cx,
+                cy,
+                radius,
+                start_angle,
+                sweep_angle,
+                width,
+                color: rgba,
+                ..
+            } => DrawOperation::arc(
+                point([*cx, *cy]),
+                *radius,
+                *start_angle,
+                *sweep_angle,
+                color(*rgba),
+                *width,
+            ),
+            Self::Text { .. } => return None,
+        };
+        Some(operation)
+    }
+}
+
+/// Version of the saved scene state format, bumped whenever old files can't be read anymore
+const STATE_FORMAT_VERSION: u32 = 3;
+
+/// Everything needed to continue a scene where it was saved, see `CityGrowScene::save_state`
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    version: u32,
+    /// Screen size the city was grown for
+    width: u32,
+    height: u32,
+    grid_size: (u32, u32),
+    phase: Phase,
+    branches: Vec<Branch>,
+    main_branches: HashSet<u32>,
+    spawn_order: Vec<u32>,
+    cells: HashMap<u32, Vec<Pos>>,
+    retiring: Option<u32>,
+    parents: HashMap<u32, u32>,
+    /// Missing in states saved before roads were recorded
+    #[serde(default)]
+    roads: HashMap<u32, Vec<(Pos, Pos)>>,
+    /// Draw history of every branch, in spawn order
+    history: Vec<ExportEntry>,
+}
+
+/// Phase of the grow → hold → reverse cycle
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Phase {
+    Growing,
+    /// The finished city is displayed statically before it starts unwinding
+    Holding {
+        elapsed: f32,
+    },
+    Reversing {
+        /// Number of history entries when the reverse started, for fraction based pacing
+        total_actions: usize,
+    },
+}
+
 struct PainterState {
     draw_history: HashMap<u32, Vec<DrawOperation>>,
     main_branches: HashSet<u32>,
+    spawn_order: Vec<u32>, // Branch ids in the order they first drew something
+    cells: HashMap<u32, Vec<Pos>>, // Grid cells occupied by each branch, freed when it is retired
+    retiring: Option<u32>, // Branch currently being erased in continuous mode
+    parents: HashMap<u32, u32>, // Child branch id -> id of the branch it branched off from
+    roads: HashMap<u32, Vec<(Pos, Pos)>>, // Grid steps (from, to) of each branch, kept with its cells
 }
 
 enum BranchOffResult {
@@ -57,33 +515,58 @@ enum BranchOffResult {
 
 struct Grid {
     data: BitVec,
+    /// District of every occupied cell (stale for free cells), empty unless territorial
+    districts: Vec<u32>,
     size_x: u32,
     size_y: u32,
+    /// Neighbors across an edge are the cells on the opposite edge (toroidal grid)
+    wrap_edges: bool,
+    /// Diagonal cells count as neighbors too
+    diagonal: bool,
 }
 
 impl Grid {
-    fn new(size_x: u32, size_y: u32) -> Self {
+    fn new(size_x: u32, size_y: u32, wrap_edges: bool, territorial: bool, diagonal: bool) -> Self {
         let mut data = BitVec::repeat(false, (size_x * size_y) as usize);
         data.shrink_to_fit();
+        let districts = if territorial {
+            vec![0; data.len()]
+        } else {
+            Vec::new()
+        };
 
         Self {
             data,
+            districts,
             size_x,
             size_y,
+            wrap_edges,
+            diagonal,
         }
     }
 
-    fn get(&self, x: u32, y: u32) -> Option<bool> {
-        if x < self.size_x && y < self.size_y {
-            Some(self.data[(y * self.size_x + x) as usize])
+    /// Steps to the cells next to a cell
+    fn directions(&self) -> &'static [Pos] {
+        if self.diagonal {
+            &POSITIONS_WITH_DIAGONALS
         } else {
-            None
+            &POSITIONS
         }
     }
 
+    /// Index of the cell in `data`, None outside the grid. Negative positions cast to `u32`
+    /// end up far outside, so callers can pass `pos.x as u32` unchecked.
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.size_x && y < self.size_y).then(|| y as usize * self.size_x as usize + x as usize)
+    }
+
+    fn get(&self, x: u32, y: u32) -> Option<bool> {
+        self.index(x, y).map(|index| self.data[index])
+    }
+
     fn set(&mut self, x: u32, y: u32, value: bool) {
-        if x < self.size_x && y < self.size_y {
-            self.data.set((y * self.size_x + x) as usize, value);
+        if let Some(index) = self.index(x, y) {
+            self.data.set(index, value);
         }
     }
 
@@ -91,28 +574,123 @@ impl Grid {
         self.data.fill(value);
     }
 
-    fn random_pos(&mut self, rng: &mut ThreadRng) -> Pos {
+    /// Mark `pos` occupied by a road of `district`
+    fn occupy(&mut self, pos: Pos, district: u32) {
+        let Some(index) = self.index(pos.x as u32, pos.y as u32) else {
+            return;
+        };
+        self.data.set(index, true);
+        if let Some(owner) = self.districts.get_mut(index) {
+            *owner = district;
+        }
+    }
+
+    /// Whether a cell next to `pos` is occupied by a district other than `district`.
+    /// Always false unless the grid is territorial.
+    fn borders_other_district(&self, pos: Pos, district: u32) -> bool {
+        if self.districts.is_empty() {
+            return false;
+        }
+        self.directions().iter().any(|&dir| {
+            self.neighbor(pos, dir)
+                .and_then(|next| self.index(next.x as u32, next.y as u32))
+                .is_some_and(|index| self.data[index] && self.districts[index] != district)
+        })
+    }
+
+    fn random_pos(&self, rng: &mut StdRng) -> Pos {
         let x = rng.random_range(0..self.size_x);
         let y = rng.random_range(0..self.size_y);
         Pos::new(x as i32, y as i32)
     }
 
-    fn get_free_neighbors(&self, pos: Pos) -> Vec<Pos> {
-        POSITIONS
+    fn free_cell_count(&self) -> usize {
+        self.data.count_zeros()
+    }
+
+    /// Pick a random unoccupied cell, or None if the grid is full
+    fn random_free_pos(&self, rng: &mut StdRng) -> Option<Pos> {
+        // Random probing is cheap while the grid is mostly empty
+        for _ in 0..16 {
+            let pos = self.random_pos(rng);
+            if self.get(pos.x as u32, pos.y as u32) == Some(false) {
+                return Some(pos);
+            }
+        }
+
+        self.data.iter_zeros().choose(rng).map(|idx| {
+            let idx = idx as u32;
+            Pos::new((idx % self.size_x) as i32, (idx / self.size_x) as i32)
+        })
+    }
+
+    /// Random free cell on the border of the grid, None if the whole border is occupied
+    fn random_free_edge_pos(&self, rng: &mut StdRng) -> Option<Pos> {
+        let (max_x, max_y) = (self.size_x as i32 - 1, self.size_y as i32 - 1);
+        let horizontal = (0..=max_x).flat_map(|x| [Pos::new(x, 0), Pos::new(x, max_y)]);
+        let vertical = (1..max_y).flat_map(|y| [Pos::new(0, y), Pos::new(max_x, y)]);
+        horizontal
+            .chain(vertical)
+            .filter(|pos| self.get(pos.x as u32, pos.y as u32) == Some(false))
+            .choose(rng)
+    }
+
+    /// Free cells next to `pos` that a road of `district` may grow into
+    fn get_free_neighbors(&self, pos: Pos, district: u32) -> Vec<Pos> {
+        self.directions()
             .iter()
             .filter_map(|&dir| {
-                pos.try_add(dir)
-                    .take_if(|new_pos| self.get(new_pos.x as u32, new_pos.y as u32) == Some(false))
+                self.neighbor(pos, dir).take_if(|new_pos| {
+                    self.get(new_pos.x as u32, new_pos.y as u32) == Some(false)
+                        && !self.borders_other_district(*new_pos, district)
+                })
             })
             .collect()
     }
 
+    /// Cell one `dir` step from `pos`, wrapped onto the opposite edge if enabled
+    fn neighbor(&self, pos: Pos, dir: Pos) -> Option<Pos> {
+        let next = pos.try_add(dir)?;
+        if self.wrap_edges {
+            Some(Pos::new(
+                next.x.rem_euclid(self.size_x as i32),
+                next.y.rem_euclid(self.size_y as i32),
+            ))
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Copy of `pos` shifted by whole grid sizes to lie closest to `anchor`, so a neighbor
+    /// across a wrapped edge ends up next to `anchor` (outside the grid). `pos` itself if the
+    /// edges don't wrap.
+    fn nearest_image(&self, pos: Pos, anchor: Pos) -> Pos {
+        if !self.wrap_edges {
+            return pos;
+        }
+        let shift = |value: i32, anchor: i32, size: u32| {
+            let size = size as i32;
+            let delta = value - anchor;
+            if delta > size / 2 {
+                value - size
+            } else if delta < -size / 2 {
+                value + size
+            } else {
+                value
+            }
+        };
+        Pos::new(
+            shift(pos.x, anchor.x, self.size_x),
+            shift(pos.y, anchor.y, self.size_y),
+        )
+    }
+
     fn is_position_valid(&self, pos: &Pos) -> bool {
         pos.x >= 0 && pos.x < self.size_x as i32 && pos.y >= 0 && pos.y < self.size_y as i32
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 struct Hsla {
     h: u8,
     s: u8,
@@ -125,6 +703,12 @@ impl Hsla {
         Self { h, s, l, a }
     }
 
+    /// Same color with the lightness moved by `shift` (fraction of the full range, -1..1)
+    fn shift_lightness(self, shift: f32) -> Self {
+        let l = (self.l as f32 + shift * 255.0).round().clamp(0.0, 255.0) as u8;
+        Self { l, ..self }
+    }
+
     const fn to_d2d_color(self) -> D2D1_COLOR_F {
         let h = (self.h as f32 / 255.0) * 360.0;
         let s = self.s as f32 / 255.0;
@@ -159,10 +743,10 @@ impl Hsla {
 }
 
 /// Position on the grid
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Pos {
-    x: i32,
-    y: i32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pos {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Pos {
@@ -188,6 +772,7 @@ impl Pos {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CityGrowSceneConfig {
     pub life_time: u16,
     pub life_time_branch: u16,
@@ -197,17 +782,156 @@ pub struct CityGrowSceneConfig {
     pub prop_branch_off_land: f32,
     pub prop_branch_off_to_main: f32,
     pub branch_fall_off: f32,
+    pub branch_spawn: BranchSpawn,
+    /// Shape of the branch-off chance over a branch's age; with a factor below 1 young branches
+    /// branch off the most, giving a dense core with sparser outskirts
+    pub branch_off_age_curve: AgeCurve,
+    pub branch_off_age_factor: f32,
     pub change_hue_new_main: u8,
+    /// Largest random hue offset in degrees of a branch-off against its parent, so districts
+    /// shade gradually instead of keeping one hue (0 = children keep the parent hue)
+    pub child_hue_drift: f32,
     pub start_branches: u8,
+    pub start_placement: StartPlacement,
+    /// Seed of the random generator, the same seed and screen size always grow the same city
+    /// (None = different every run)
+    pub seed: Option<u64>,
+    /// Hue in degrees (0-360) shared by the start branches, None picks every hue at random
+    pub start_hue_base: Option<f32>,
+    /// Width in degrees of the hue range around `start_hue_base` the start branches pick from
+    pub start_hue_spread: f32,
+    /// Colors main branches pick from as (hue in degrees, saturation 0-1, lightness 0-1),
+    /// overriding the start hue and main branch saturation/lightness settings when not empty.
+    /// Side branches keep their main branch's hue.
+    pub palette: Vec<(f32, f32, f32)>,
+    /// Named palettes used in turn, one per city, in place of `palette` when not empty
+    pub palettes: Vec<Palette>,
     pub max_steps_back: u16,
     pub lightness_default: u8,
     pub lightness_branch: u8,
     pub saturation_main: u8,
     pub saturation_branch: u8,
     pub city_rect_alpha: f32,
+    /// Grid density: a cell is `2 * scale` pixels wide. Road widths are set independently
+    /// through `main_thickness` and `branch_thickness`.
     pub scale: f32,
+    /// Treat `scale` as the size at 100% display scaling and multiply it by the monitor's
+    /// scaling factor, so the city looks the same size on high-DPI displays. Off keeps `scale`
+    /// in physical pixels.
+    pub scale_with_dpi: bool,
+    /// Road thickness of main branches, relative to `scale`
+    pub main_thickness: f32,
+    /// Road thickness of branches that branched off, relative to `scale`
+    pub branch_thickness: f32,
+    /// Maximum number of history entries erased per frame
     pub reverse_actions_per_frame: usize,
+    /// Fraction of the finished city erased per second (0 = always erase
+    /// `reverse_actions_per_frame` entries). Makes the reverse take about `1 / fraction`
+    /// seconds however the history is split across branches, still capped per frame.
+    pub reverse_fraction_per_second: f32,
+    /// How strongly land mode keeps its expansion direction; higher values give longer,
+    /// straighter highways, values near 1 make them meander like city mode
     pub land_directional_bias: f32,
+    /// Minimum number of steps a branch stays in land mode before it may turn back into a city
+    pub land_expand_steps: u16,
+    /// Growth steps per second, independent of the frame rate (0 = one step per frame)
+    pub growth_steps_per_second: f32,
+    /// Seconds the finished city is shown before it is reversed (or cleared with the restart
+    /// completion mode)
+    pub hold_before_reverse: f32,
+    pub block_corner_radius: f32,
+    /// Inset in pixels of city blocks on every side, at most half of `scale` (0 = blocks fill
+    /// their whole grid cell). Only the blocks shrink, roads keep their position and width.
+    pub block_margin: f32,
+    /// Opacity of city blocks at the top of the screen, blocks fade from `city_rect_alpha` at
+    /// the bottom to this towards the horizon (None = flat color). Gradient blocks ignore
+    /// `block_corner_radius`.
+    pub block_gradient_top_alpha: Option<f32>,
+    /// Brightness pulses per second of the finished city while it is held (0 = off)
+    pub pulse_speed: f32,
+    /// How much the brightness dips at the bottom of a pulse (0 = off, 1 = down to black).
+    /// Pulsing redraws the whole scene every frame during the hold, so it costs some GPU time.
+    pub pulse_depth: f32,
+    /// Preference for east/west moves (positive) or north/south moves (negative), in -1..1
+    pub horizontal_bias: f32,
+    /// Direction growth drifts towards as (east, north), each in -1..1, e.g. (1, 1) sprawls to
+    /// the northeast. (0, 0) keeps moves uniform.
+    pub growth_bias: (f32, f32),
+    pub on_life_end: LifeEndBehavior,
+    /// Round the corners of the finished roads while the city is held and reversed
+    pub rounded_joins: bool,
+    /// Draw with smooth (antialiased) edges instead of pixel-perfect ones, mostly useful with
+    /// `curved_roads`. Erased roads may leave a faint outline.
+    pub antialiasing: bool,
+    /// Draw roads as smooth curves through the grid points instead of straight segments.
+    /// Road tips trail half a cell behind their branch and are completed when it stops.
+    pub curved_roads: bool,
+    /// Let roads leaving the screen on one edge continue on the opposite edge instead of
+    /// ending there
+    pub wrap_edges: bool,
+    /// Keep districts apart: roads never grow into a cell touching another district's roads.
+    /// A district is a start branch together with everything that branched off from it.
+    pub territorial: bool,
+    /// Let roads also grow diagonally, for a less rectangular spread. Diagonal steps get no
+    /// city blocks beside them, and diagonal roads may cross each other between cells.
+    pub allow_diagonal: bool,
+    pub reverse_order: ReverseOrder,
+    pub reverse_direction: ReverseDirection,
+    /// Don't erase a branch while roads branched off from it are still visible, so the city
+    /// retracts from its leaves towards its trunks. Only affects the simultaneous reverse order
+    /// (by-branch order already erases children first). Costs a pass over all recorded
+    /// parent/child links every reverse frame.
+    pub reverse_respects_tree: bool,
+    pub completion: CompletionMode,
+    /// Fraction of the grid that may be occupied in continuous mode before the oldest
+    /// finished branches are erased to make room
+    pub continuous_max_fill: f32,
+    /// Stop after this many cities have grown and been erased, closing the window (None = run
+    /// forever). Continuous mode never completes a cycle.
+    pub max_cycles: Option<u32>,
+    /// Upper limit for the recorded draw operations of a city (0 = unlimited). Reaching it ends
+    /// growth early in reverse mode and retires the oldest branches in continuous mode, which
+    /// bounds memory use with long life times.
+    pub max_total_actions: usize,
+    /// Upper limit for the number of branches growing at the same time (0 = unlimited).
+    /// Branches stop branching off while it is reached, which bounds the work per step on
+    /// large grids.
+    pub max_active_branches: usize,
+    /// How much of the previous frame is kept in each new one while growing (0 = off),
+    /// which makes new roads fade in. Only applies during growth: the reverse animation erases
+    /// exact pixels, so the blur is switched off before it starts.
+    pub motion_blur: f32,
+    /// Opacity of the whole wallpaper over the desktop (1 = opaque). Below 1 the city is drawn
+    /// over a transparent background instead of black, so the desktop shows through.
+    pub global_alpha: f32,
+    /// Draw roads created in land mode (highways) in a different shade than city streets
+    pub distinguish_modes: bool,
+    /// Lightness change of land mode roads when `distinguish_modes` is on (-1..1)
+    pub land_color_shift: f32,
+    /// Image shown behind the city instead of black, stretched to the screen (None = black).
+    /// Erased roads reveal the image again.
+    pub background_image: Option<PathBuf>,
+    /// Text shown on top of the city at all times, e.g. a quote or a title (None = off)
+    pub overlay_text: Option<String>,
+    pub overlay_font: String,
+    pub overlay_size: f32,
+    /// Overlay text color as RGBA, each channel in 0..1
+    pub overlay_color: [f32; 4],
+    pub overlay_anchor: TextAnchor,
+    /// Show the frame rate and branch counts in the top-left corner
+    pub show_debug_overlay: bool,
+    /// Draw faint lines along the grid cell boundaries on top of the scene
+    pub show_grid: bool,
+    /// Label every branch covering at least this many grid cells with a district name at its
+    /// center, on top of the roads. The label stays until the branch is erased (0 = off).
+    pub district_label_min_cells: u32,
+    /// Names the district labels pick from, in `overlay_font`
+    pub district_label_names: Vec<String>,
+    /// District label color as RGBA, each channel in 0..1
+    pub district_label_color: [f32; 4],
+    /// Shift the road colors with the local time: cool at night and around midday, warm at
+    /// sunrise and sunset. Only changes how roads are drawn, erasing works as before.
+    pub time_of_day_tint: bool,
 }
 
 impl Default for CityGrowSceneConfig {
@@ -221,8 +945,18 @@ impl Default for CityGrowSceneConfig {
             prop_branch_off_land: 0.06,
             prop_branch_off_to_main: 0.02,
             branch_fall_off: 50.0,
+            branch_spawn: BranchSpawn::Tip,
+            branch_off_age_curve: AgeCurve::Constant,
+            branch_off_age_factor: 0.25,
             change_hue_new_main: 11,
+            child_hue_drift: 0.0,
             start_branches: 3,
+            start_placement: StartPlacement::Random,
+            seed: None,
+            start_hue_base: None,
+            start_hue_spread: 60.0,
+            palette: Vec::new(),
+            palettes: Vec::new(),
             max_steps_back: 50,
             lightness_default: 140,
             lightness_branch: 60,
@@ -230,57 +964,423 @@ impl Default for CityGrowSceneConfig {
             saturation_branch: 255,
             city_rect_alpha: 0.35,
             scale: 2.0,
+            scale_with_dpi: false,
+            main_thickness: 1.0,
+            branch_thickness: 1.0,
             reverse_actions_per_frame: 50,
+            reverse_fraction_per_second: 0.0,
             land_directional_bias: 3.0,
+            land_expand_steps: 0,
+            growth_steps_per_second: 0.0,
+            hold_before_reverse: 2.0,
+            block_corner_radius: 0.0,
+            block_gradient_top_alpha: None,
+            block_margin: 0.0,
+            pulse_speed: 0.5,
+            pulse_depth: 0.0,
+            horizontal_bias: 0.0,
+            growth_bias: (0.0, 0.0),
+            on_life_end: LifeEndBehavior::Stop,
+            rounded_joins: false,
+            antialiasing: false,
+            curved_roads: false,
+            wrap_edges: false,
+            territorial: false,
+            allow_diagonal: false,
+            reverse_order: ReverseOrder::Simultaneous,
+            reverse_direction: ReverseDirection::NewestFirst,
+            reverse_respects_tree: false,
+            completion: CompletionMode::Reverse,
+            continuous_max_fill: 0.5,
+            max_cycles: None,
+            max_total_actions: 0,
+            max_active_branches: 0,
+            motion_blur: 0.0,
+            global_alpha: 1.0,
+            distinguish_modes: false,
+            land_color_shift: 0.2,
+            background_image: None,
+            overlay_text: None,
+            overlay_font: "Segoe UI".to_string(),
+            overlay_size: 24.0,
+            overlay_color: [1.0, 1.0, 1.0, 0.8],
+            overlay_anchor: TextAnchor::BottomRight,
+            show_debug_overlay: false,
+            district_label_min_cells: 0,
+            district_label_names: [
+                "Downtown",
+                "Old Town",
+                "Harbor",
+                "Midtown",
+                "Riverside",
+                "Uptown",
+                "Market",
+                "Hillside",
+            ]
+            .map(String::from)
+            .to_vec(),
+            district_label_color: [1.0, 1.0, 1.0, 0.7],
+            show_grid: false,
+            time_of_day_tint: false,
         }
     }
 }
 
+/// Fail with a readable error if `value` lies outside `range`
+fn check_range(name: &str, value: f32, range: RangeInclusive<f32>) -> Result<()> {
+    if !range.contains(&value) {
+        bail!(
+            "{} must be between {} and {}, got {}",
+            name,
+            range.start(),
+            range.end(),
+            value
+        );
+    }
+    Ok(())
+}
+
+/// Color multiplied into the roads at `hour` (0..24) of the day, see `TIME_OF_DAY_TINTS`
+fn time_of_day_tint(hour: f32) -> D2D1_COLOR_F {
+    let hour = hour.rem_euclid(24.0);
+    let (from, to) = TIME_OF_DAY_TINTS
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|(_, (to_hour, _))| hour <= *to_hour)
+        .unwrap_or((TIME_OF_DAY_TINTS[0], TIME_OF_DAY_TINTS[1]));
+    let t = (hour - from.0) / (to.0 - from.0);
+    let [r, g, b] = std::array::from_fn(|i| from.1[i] + (to.1[i] - from.1[i]) * t);
+    D2D1_COLOR_F { r, g, b, a: 1.0 }
+}
+
+/// Current local time of day in hours (0..24), fractional minutes and seconds included
+fn local_hour() -> f32 {
+    let time = unsafe { GetLocalTime() };
+    time.wHour as f32 + time.wMinute as f32 / 60.0 + time.wSecond as f32 / 3600.0
+}
+
+/// Point half way between `a` and `b`
+fn midpoint(a: Vector2, b: Vector2) -> Vector2 {
+    Vector2 {
+        X: (a.X + b.X) / 2.0,
+        Y: (a.Y + b.Y) / 2.0,
+    }
+}
+
 impl CityGrowSceneConfig {
+    /// Reject values the simulation can't work with instead of silently clamping them
+    pub fn validate(&self) -> Result<()> {
+        check_range("scale", self.scale, f32::MIN_POSITIVE..=f32::MAX)?;
+        for (name, value) in [
+            ("prop_city_to_land", self.prop_city_to_land),
+            ("prop_land_to_city", self.prop_land_to_city),
+            ("prop_branch_off_city", self.prop_branch_off_city),
+            ("prop_branch_off_land", self.prop_branch_off_land),
+            ("prop_branch_off_to_main", self.prop_branch_off_to_main),
+            ("city_rect_alpha", self.city_rect_alpha),
+            ("pulse_depth", self.pulse_depth),
+            ("motion_blur", self.motion_blur),
+            ("global_alpha", self.global_alpha),
+            ("continuous_max_fill", self.continuous_max_fill),
+        ] {
+            check_range(name, value, 0.0..=1.0)?;
+        }
+        for (name, value) in [
+            ("branch_fall_off", self.branch_fall_off),
+            ("branch_off_age_factor", self.branch_off_age_factor),
+            ("growth_steps_per_second", self.growth_steps_per_second),
+            ("hold_before_reverse", self.hold_before_reverse),
+            ("block_corner_radius", self.block_corner_radius),
+            ("block_margin", self.block_margin),
+            ("pulse_speed", self.pulse_speed),
+            (
+                "reverse_fraction_per_second",
+                self.reverse_fraction_per_second,
+            ),
+            ("start_hue_spread", self.start_hue_spread),
+            ("child_hue_drift", self.child_hue_drift),
+        ] {
+            check_range(name, value, 0.0..=f32::MAX)?;
+        }
+        for (name, value) in [
+            ("main_thickness", self.main_thickness),
+            ("branch_thickness", self.branch_thickness),
+        ] {
+            check_range(name, value, f32::MIN_POSITIVE..=f32::MAX)?;
+        }
+        // Land mode picks a random neighbor with a chance of 1 / land_directional_bias
+        check_range(
+            "land_directional_bias",
+            self.land_directional_bias,
+            1.0..=f32::MAX,
+        )?;
+        check_range("horizontal_bias", self.horizontal_bias, -1.0..=1.0)?;
+        check_range("growth_bias.0", self.growth_bias.0, -1.0..=1.0)?;
+        check_range("growth_bias.1", self.growth_bias.1, -1.0..=1.0)?;
+        check_range("land_color_shift", self.land_color_shift, -1.0..=1.0)?;
+        check_range(
+            "overlay_size",
+            self.overlay_size,
+            f32::MIN_POSITIVE..=f32::MAX,
+        )?;
+        if let Some(alpha) = self.block_gradient_top_alpha {
+            check_range("block_gradient_top_alpha", alpha, 0.0..=1.0)?;
+        }
+        let palette_colors = self.palettes.iter().flat_map(|palette| &palette.colors);
+        for (_, saturation, lightness) in self.palette.iter().chain(palette_colors) {
+            check_range("palette saturation", *saturation, 0.0..=1.0)?;
+            check_range("palette lightness", *lightness, 0.0..=1.0)?;
+        }
+        Ok(())
+    }
+
+    /// Colors of the palette with the given index in `palettes`, wrapping around, or `palette`
+    /// when no named palettes are configured
+    fn active_palette(&self, palette_index: usize) -> &[(f32, f32, f32)] {
+        if self.palettes.is_empty() {
+            &self.palette
+        } else {
+            &self.palettes[palette_index % self.palettes.len()].colors
+        }
+    }
+
+    /// Random main branch color from the active palette, None if no palette is configured
+    fn palette_color(&self, palette_index: usize, rng: &mut StdRng) -> Option<Hsla> {
+        let &(hue, saturation, lightness) = self.active_palette(palette_index).choose(rng)?;
+        Some(Hsla::new(
+            (hue.rem_euclid(360.0) / 360.0 * 256.0) as u8,
+            (saturation * 255.0).round() as u8,
+            (lightness * 255.0).round() as u8,
+            255,
+        ))
+    }
+
     pub fn branch_chance(&self, mode: BranchMode) -> f32 {
         match mode {
             BranchMode::City => self.prop_branch_off_city,
             BranchMode::Land => self.prop_branch_off_land,
         }
     }
+
+    /// Multiplier of the branch-off chance for a branch at `age` of its `life_time`
+    pub fn branch_age_multiplier(&self, age: u16, life_time: u16) -> f32 {
+        let progress = (age as f32 / life_time.max(1) as f32).clamp(0.0, 1.0);
+        let factor = self.branch_off_age_factor.max(0.0);
+        match self.branch_off_age_curve {
+            AgeCurve::Constant => 1.0,
+            AgeCurve::Linear => 1.0 + (factor - 1.0) * progress,
+            AgeCurve::Exponential => factor.powf(progress),
+        }
+    }
+}
+
+/// What a branch does when it reaches the end of its life time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LifeEndBehavior {
+    /// Stop growing
+    Stop,
+    /// Expand in land mode for `life_time_branch` more steps, then stop
+    ConvertToLand,
+    /// Spawn a couple of short branch-offs from the tip, then stop
+    SpawnChildren,
+}
+
+/// Order in which branches are erased during the reverse animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReverseOrder {
+    /// All branches unwind together, side branches before main branches
+    Simultaneous,
+    /// Branches unwind fully one at a time, last spawned first
+    ByBranch,
+}
+
+/// Which end of each branch is erased first during the reverse animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReverseDirection {
+    /// Roads retract from their tips back to where they started
+    NewestFirst,
+    /// Roads dissolve from where they started out to their tips
+    OldestFirst,
+}
+
+impl ReverseDirection {
+    /// Take up to `count` entries off a branch history, in the order they are erased
+    fn drain(self, history: &mut Vec<DrawOperation>, count: usize) -> Vec<DrawOperation> {
+        let count = count.min(history.len());
+        match self {
+            Self::NewestFirst => history.drain(history.len() - count..).rev().collect(),
+            Self::OldestFirst => history.drain(..count).collect(),
+        }
+    }
+}
+
+/// How the branch-off chance changes over a branch's life time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgeCurve {
+    /// Same chance at every age
+    Constant,
+    /// Chance moves linearly from 1x at birth to `branch_off_age_factor` x at the end of life
+    Linear,
+    /// Chance moves exponentially from 1x at birth to `branch_off_age_factor` x at the end of life
+    Exponential,
+}
+
+/// Where along a branch its branch-offs sprout from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BranchSpawn {
+    /// Always from the growing end
+    Tip,
+    /// From a random cell among the last `n` cells of the branch
+    NearTip(u16),
+    /// From any cell of the branch that still has a free neighbor, giving ladder-like streets
+    RandomBody,
+}
+
+/// Where the start branches of a city are placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StartPlacement {
+    /// Random free cells
+    Random,
+    /// Evenly spread on a small circle around the center (a single branch starts at the center)
+    Center,
+    /// In the corners, any branches beyond the fourth start at random cells
+    Corners,
+    /// Evenly spaced in a grid of about sqrt(n) x sqrt(n) cells
+    Grid,
+}
+
+/// What happens once the city is complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompletionMode {
+    /// Hold the finished city, unwind it and start over
+    Reverse,
+    /// Never finish: new main branches keep spawning from the screen edges while the oldest
+    /// finished roads are erased, so the city keeps changing without ever clearing
+    Continuous,
+    /// Hold the finished city, then clear the screen at once and start over
+    Restart,
+}
+
+/// Named list of main branch colors, see `CityGrowSceneConfig::palettes`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Palette {
+    pub name: String,
+    /// Colors as (hue in degrees, saturation 0-1, lightness 0-1)
+    pub colors: Vec<(f32, f32, f32)>,
 }
 
+/// Number of branch-offs spawned by LifeEndBehavior::SpawnChildren
+const LIFE_END_CHILDREN: usize = 2;
+
+/// Seed of the thumbnail city when no seed is configured
+const THUMBNAIL_SEED: u64 = 0x00C1_7960;
+
+/// Distance in pixels between the overlay text and the screen edges
+const OVERLAY_TEXT_PADDING: f32 = 32.0;
+
+/// Font size of the district labels
+const DISTRICT_LABEL_FONT_SIZE: f32 = 18.0;
+
+/// Font size of the frame rate / branch count overlay
+const DEBUG_OVERLAY_FONT_SIZE: f32 = 16.0;
+const DEBUG_OVERLAY_COLOR: D2D1_COLOR_F = D2D1_COLOR_F {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.9,
+};
+/// Weight of the newest frame in the smoothed frame rate of the debug overlay
+const DEBUG_FPS_SMOOTHING: f32 = 0.05;
+
+/// Most growth steps taken in one frame with `growth_steps_per_second` at each quality level,
+/// lowest first, so a long stall doesn't turn into a burst of steps
+const MAX_GROWTH_STEPS_PER_FRAME: [u32; MAX_QUALITY_LEVEL as usize + 1] = [2, 4, 8];
+
+/// Growth steps per frame without `growth_steps_per_second` at quality level 0
+const LOW_QUALITY_STEPS_PER_FRAME: f32 = 0.5;
+
+/// Weight of a move straight against `growth_bias` (relative to 1 for a move across it),
+/// so branches can still turn back when nothing else is free
+const MIN_GROWTH_BIAS_WEIGHT: f32 = 0.1;
+
+/// Tint of the road colors over the day as (hour, [r, g, b]), blended linearly in between
+/// and wrapping around at midnight
+const TIME_OF_DAY_TINTS: [(f32, [f32; 3]); 5] = [
+    (0.0, [0.70, 0.78, 1.00]),  // Night
+    (6.5, [1.00, 0.82, 0.66]),  // Sunrise
+    (12.5, [0.90, 0.96, 1.00]), // Midday
+    (19.0, [1.00, 0.70, 0.52]), // Sunset
+    (24.0, [0.70, 0.78, 1.00]), // Night again
+];
+
+/// Upper bound on growth steps when growing a city without a window, in case the
+/// configuration keeps spawning branches forever
+const OFFSCREEN_MAX_STEPS: usize = 1_000_000;
+
 /// Branch mode
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BranchMode {
     City, // Random walk
     Land, // Directional expansion
 }
 
 /// A growing branch
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct Branch {
     id: u32,
     pos: Pos, // Current position
     mode: BranchMode,
     expand_direction: Pos,
     own_fields: Vec<Pos>,
+    // Indices into own_fields that may still have free neighbors, oldest first. Entries found
+    // boxed in are dropped until cells are freed again (see reopen_fields).
+    #[serde(skip)]
+    open_fields: Vec<u32>,
     age: u16,
     life_time: u16,
     color: Hsla,
+    thickness: f32,  // Road thickness relative to the scale
+    terminal: bool,  // In the land expansion phase after its life time ended
+    land_steps: u16, // Steps taken since the branch last entered land mode
+    district: u32,   // Id of the start branch this one descends from
+    // Spawned at another branch's life end, spawns no children at its own. Missing in states
+    // saved before it was recorded.
+    #[serde(default)]
+    life_end_child: bool,
 }
 
 impl Branch {
-    fn new(pos: Pos, config: &CityGrowSceneConfig, rng: &mut ThreadRng) -> Self {
-        let hue: u8 = rng.random_range(0..=255);
+    fn new(pos: Pos, config: &CityGrowSceneConfig, palette_index: usize, rng: &mut StdRng) -> Self {
+        let hue: u8 = match config.start_hue_base {
+            Some(base) => {
+                let half_spread = config.start_hue_spread.abs() / 2.0;
+                let degrees = base + rng.random_range(-half_spread..=half_spread);
+                (degrees.rem_euclid(360.0) / 360.0 * 256.0) as u8
+            }
+            None => rng.random_range(0..=255),
+        };
 
         // Pre-calculate colors
-        let color = Hsla::new(hue, config.saturation_main, config.lightness_default, 255);
+        let color = config.palette_color(palette_index, rng).unwrap_or_else(|| {
+            Hsla::new(hue, config.saturation_main, config.lightness_default, 255)
+        });
 
+        let id = rng.random();
         Self {
-            id: rng.random(),
+            id,
             pos,
             mode: BranchMode::City,
             expand_direction: Pos::new(0, 0),
             own_fields: vec![pos],
+            open_fields: vec![0],
             age: 0,
             life_time: config.life_time,
             color,
+            thickness: config.main_thickness,
+            terminal: false,
+            land_steps: 0,
+            district: id,
+            life_end_child: false,
         }
     }
 
@@ -288,13 +1388,16 @@ impl Branch {
         self,
         grid: &Grid,
         config: &CityGrowSceneConfig,
-        rng: &mut ThreadRng,
+        rng: &mut StdRng,
     ) -> Option<(Branch, Pos, Pos, Pos)> {
         if self.age >= self.life_time {
             return None;
         }
 
-        let (new_branch, next_move) = if (self.life_time - self.age) < config.life_time_branch {
+        let (new_branch, next_move) = if self.terminal {
+            // Keep expanding in land mode until the terminal phase ends
+            self
+        } else if (self.life_time - self.age) < config.life_time_branch {
             Self {
                 mode: BranchMode::City,
                 ..self
@@ -313,23 +1416,27 @@ impl Branch {
             .unwrap_or(new_branch.pos);
         let new_branch = Self {
             pos: next_move,
+            open_fields: {
+                let mut open_fields = new_branch.open_fields;
+                open_fields.push(new_branch.own_fields.len() as u32);
+                open_fields
+            },
             own_fields: {
                 let mut fields = new_branch.own_fields;
                 fields.push(next_move);
                 fields
             },
             age: new_branch.age + 1,
+            land_steps: match new_branch.mode {
+                BranchMode::Land => new_branch.land_steps.saturating_add(1),
+                BranchMode::City => 0,
+            },
             ..new_branch
         };
         Some((new_branch, pos, next_move, own_fields_tip))
     }
 
-    fn transition_modes(
-        self,
-        grid: &Grid,
-        config: &CityGrowSceneConfig,
-        rng: &mut ThreadRng,
-    ) -> Self {
+    fn transition_modes(self, grid: &Grid, config: &CityGrowSceneConfig, rng: &mut StdRng) -> Self {
         if self.mode == BranchMode::City && rng.random::<f32>() < config.prop_city_to_land {
             return Self {
                 expand_direction: self
@@ -338,7 +1445,10 @@ impl Branch {
                 mode: BranchMode::Land,
                 ..self
             };
-        } else if self.mode == BranchMode::Land && rng.random::<f32>() < config.prop_land_to_city {
+        } else if self.mode == BranchMode::Land
+            && self.land_steps >= config.land_expand_steps
+            && rng.random::<f32>() < config.prop_land_to_city
+        {
             return Self {
                 mode: BranchMode::City,
                 age: rng.random_range(0..=self.age),
@@ -348,35 +1458,63 @@ impl Branch {
         self
     }
 
-    fn expand_direction(&self, grid: &Grid, rng: &mut ThreadRng) -> Option<Pos> {
-        let available_neighbors = grid.get_free_neighbors(self.pos);
+    /// Start the terminal land expansion phase, None if the branch already went through it
+    fn convert_to_land(
+        self,
+        grid: &Grid,
+        config: &CityGrowSceneConfig,
+        rng: &mut StdRng,
+    ) -> Option<Self> {
+        if self.terminal {
+            return None;
+        }
+        Some(Self {
+            expand_direction: self
+                .expand_direction(grid, rng)
+                .unwrap_or(self.expand_direction),
+            mode: BranchMode::Land,
+            age: 0,
+            life_time: config.life_time_branch,
+            terminal: true,
+            ..self
+        })
+    }
+
+    fn expand_direction(&self, grid: &Grid, rng: &mut StdRng) -> Option<Pos> {
+        let available_neighbors = grid.get_free_neighbors(self.pos, self.district);
         if available_neighbors.is_empty() {
             return None;
         }
         let target_position = available_neighbors[rng.random_range(0..available_neighbors.len())];
-        target_position
+        grid.nearest_image(target_position, self.pos)
             .try_sub(self.pos)
             .filter(|pos| grid.is_position_valid(pos))
     }
 
+    /// Consider every own field for backtracking again, after grid cells were freed
+    fn reopen_fields(&mut self) {
+        self.open_fields = (0..self.own_fields.len() as u32).collect();
+    }
+
     /// If no free neighbors, try backtracking up to max_steps_back to find a position with free neighbors.
     /// If such a position is not found, return None to indicate the branch should die.
-    fn set_next_position(self, grid: &Grid, config: &CityGrowSceneConfig) -> Option<Self> {
-        if grid.get_free_neighbors(self.pos).is_empty() {
-            let num_positions_to_search =
-                (config.max_steps_back as usize).min(self.own_fields.len());
-            let new_position = self
+    fn set_next_position(mut self, grid: &Grid, config: &CityGrowSceneConfig) -> Option<Self> {
+        if grid.get_free_neighbors(self.pos, self.district).is_empty() {
+            // Boxed in positions stay boxed in while no cells are freed, so each one is only
+            // checked once
+            let oldest = self
                 .own_fields
-                .iter()
-                .rev()
-                .take(num_positions_to_search)
-                .find(|pos| !grid.get_free_neighbors(**pos).is_empty())
-                .copied();
-            if let Some(new_pos) = new_position {
-                return Some(Branch {
-                    pos: new_pos,
-                    ..self
-                });
+                .len()
+                .saturating_sub(config.max_steps_back as usize);
+            while let Some(&index) = self.open_fields.last() {
+                if (index as usize) < oldest {
+                    return None;
+                }
+                let pos = self.own_fields[index as usize];
+                if !grid.get_free_neighbors(pos, self.district).is_empty() {
+                    return Some(Branch { pos, ..self });
+                }
+                self.open_fields.pop();
             }
             return None;
         }
@@ -387,13 +1525,12 @@ impl Branch {
         self,
         grid: &Grid,
         config: &CityGrowSceneConfig,
-        rng: &mut ThreadRng,
+        rng: &mut StdRng,
     ) -> (Self, Pos) {
-        let neighbors = grid.get_free_neighbors(self.pos);
+        let neighbors = grid.get_free_neighbors(self.pos, self.district);
         if self.mode == BranchMode::Land {
-            let preferred = self
-                .pos
-                .try_add(self.expand_direction)
+            let preferred = grid
+                .neighbor(self.pos, self.expand_direction)
                 .filter(|pos| grid.is_position_valid(pos));
 
             if let Some(preferred) = preferred.filter(|p| neighbors.contains(p)) {
@@ -401,12 +1538,14 @@ impl Branch {
                     neighbors.len() as u32,
                     (neighbors.len() as f32 * config.land_directional_bias).round() as u32,
                 ) {
-                    return (self, *neighbors.choose(rng).unwrap());
+                    let next = self.choose_neighbor(grid, &neighbors, config, rng);
+                    return (self, next);
                 }
                 return (self, preferred);
             }
-            let new_target = *neighbors.choose(rng).unwrap();
-            let new_direction = new_target
+            let new_target = self.choose_neighbor(grid, &neighbors, config, rng);
+            let new_direction = grid
+                .nearest_image(new_target, self.pos)
                 .try_sub(self.pos)
                 .unwrap_or(self.expand_direction);
             (
@@ -417,49 +1556,124 @@ impl Branch {
                 new_target,
             )
         } else {
-            (self, *neighbors.choose(rng).unwrap())
+            let next = self.choose_neighbor(grid, &neighbors, config, rng);
+            (self, next)
+        }
+    }
+
+    /// Pick one of the free neighbors, weighted towards the axis favored by `horizontal_bias`
+    /// and the direction of `growth_bias`
+    fn choose_neighbor(
+        &self,
+        grid: &Grid,
+        neighbors: &[Pos],
+        config: &CityGrowSceneConfig,
+        rng: &mut StdRng,
+    ) -> Pos {
+        let bias = config.horizontal_bias.clamp(-1.0, 1.0);
+        let (east, north) = config.growth_bias;
+        if bias == 0.0 && east == 0.0 && north == 0.0 {
+            return *neighbors.choose(rng).unwrap();
         }
+
+        // At full bias, moves along the favored axis are 5 times as likely
+        let horizontal_weight = 1.0 + bias.max(0.0) * 4.0;
+        let vertical_weight = 1.0 + (-bias).max(0.0) * 4.0;
+        *neighbors
+            .choose_weighted(rng, |neighbor| {
+                let axis_weight = if neighbor.y == self.pos.y {
+                    horizontal_weight
+                } else if neighbor.x == self.pos.x {
+                    vertical_weight
+                } else {
+                    // Diagonal steps (`allow_diagonal`) lie on neither axis
+                    1.0
+                };
+                // Grid y grows downwards (south)
+                let step = grid.nearest_image(*neighbor, self.pos);
+                let dot =
+                    (step.x - self.pos.x) as f32 * east - (step.y - self.pos.y) as f32 * north;
+                axis_weight * (1.0 + dot).max(MIN_GROWTH_BIAS_WEIGHT)
+            })
+            .unwrap()
+    }
+
+    /// Cell of this branch a branch-off sprouts from, according to `branch_spawn`
+    fn branch_origin(
+        &self,
+        grid: &Grid,
+        config: &CityGrowSceneConfig,
+        rng: &mut StdRng,
+    ) -> Option<Pos> {
+        let candidates = match config.branch_spawn {
+            BranchSpawn::Tip => return self.own_fields.last().copied(),
+            BranchSpawn::NearTip(count) => {
+                let count = (count as usize).clamp(1, self.own_fields.len());
+                &self.own_fields[self.own_fields.len() - count..]
+            }
+            BranchSpawn::RandomBody => &self.own_fields[..],
+        };
+        candidates
+            .iter()
+            .filter(|pos| !grid.get_free_neighbors(**pos, self.district).is_empty())
+            .choose(rng)
+            .copied()
     }
 
     fn try_branch_off(
         self,
         grid: &Grid,
         config: &CityGrowSceneConfig,
-        rng: &mut ThreadRng,
+        rng: &mut StdRng,
     ) -> BranchOffResult {
         if self.own_fields.len() <= 1 {
             return BranchOffResult::Failure { branch: self };
         }
-        let search_pos = *self.own_fields.last().unwrap();
-        let selected_neighbor =
-            if let Some(neighbor) = grid.get_free_neighbors(search_pos).choose(rng) {
-                *neighbor
-            } else {
-                return BranchOffResult::Failure { branch: self };
-            };
+        let Some(search_pos) = self.branch_origin(grid, config, rng) else {
+            return BranchOffResult::Failure { branch: self };
+        };
+        let selected_neighbor = if let Some(neighbor) = grid
+            .get_free_neighbors(search_pos, self.district)
+            .choose(rng)
+        {
+            *neighbor
+        } else {
+            return BranchOffResult::Failure { branch: self };
+        };
 
+        let hue = if config.child_hue_drift > 0.0 {
+            let drift = config.child_hue_drift;
+            let degrees = self.color.h as f32 / 256.0 * 360.0 + rng.random_range(-drift..=drift);
+            // Rounded, truncating would lose up to a step per generation and drift hues down
+            (degrees / 360.0 * 256.0).round().rem_euclid(256.0) as u8
+        } else {
+            self.color.h
+        };
         let child = Self {
             id: rng.random(),
             pos: selected_neighbor,
             mode: BranchMode::City,
             expand_direction: Pos::new(0, 0),
             own_fields: vec![selected_neighbor],
+            open_fields: vec![0],
             age: 0,
             life_time: config.life_time_branch,
-            color: Hsla::new(
-                self.color.h,
-                config.saturation_branch,
-                config.lightness_branch,
-                255,
-            ),
+            color: Hsla::new(hue, config.saturation_branch, config.lightness_branch, 255),
+            thickness: config.branch_thickness,
+            terminal: false,
+            land_steps: 0,
+            district: self.district,
+            life_end_child: false,
         };
 
         let branch_event = Event::BranchOff {
             child_id: child.id,
+            parent_id: self.id,
             parent_pos: search_pos,
             child_pos: selected_neighbor,
             parent_mode: self.mode,
             child_color: child.color,
+            child_thickness: child.thickness,
         };
 
         BranchOffResult::Success {
@@ -475,78 +1689,663 @@ pub struct CityGrowScene {
     config: CityGrowSceneConfig,
     grid: Grid,
     branch_list: Vec<Branch>,
-    reverse_running: bool,
+    phase: Phase,
     painter_state: PainterState,
 
     needs_initial_clear: bool,
+    /// Redraw the whole history on the next frame (after restoring a saved state)
+    needs_replay: bool,
+    /// `max_total_actions` was reached in this city (logged once)
+    action_cap_reached: bool,
+    /// `max_active_branches` was reached in this city (logged once)
+    branch_cap_reached: bool,
+    /// Phase transitions not yet collected by `take_phase_changes`
+    phase_changes: Vec<AnimationPhase>,
+    debug_grid: bool,
+    overlay_format: Option<IDWriteTextFormat>,
+    debug_overlay_format: Option<IDWriteTextFormat>,
+    district_label_format: Option<IDWriteTextFormat>,
+    /// Smoothed frame rate shown by the debug overlay
+    debug_fps: f32,
+    /// Time not yet spent on growth steps with `growth_steps_per_second`
+    growth_time_accumulator: f32,
+    quality_level: u8,
+    paused: bool,
+    /// `config.scale` as configured, before DPI scaling
+    base_scale: f32,
+    /// DPI of the monitor the scene is shown on
+    dpi: u32,
+    /// `background_image` was edited, the renderer's background has to be replaced
+    background_changed: bool,
+    /// Cities that grew and were erased completely since the start
+    completed_cycles: u32,
+    /// `max_cycles` was reached, nothing is drawn anymore
+    finished: bool,
+    /// Index in `palettes` of the palette the current city picks its colors from, None
+    /// before the first city
+    palette_index: Option<usize>,
+    /// End of a hold in which nothing changes on screen. Until then the scene stops
+    /// animating, so the app can stop its frame timer.
+    idle_until: Option<Instant>,
+    /// The static hold is too long to end at an `Instant` (e.g. 1e20 seconds for "forever"),
+    /// the scene stays idle without a wake-up until a new city starts
+    idle_forever: bool,
+    /// The grid lines overlay has to be recorded again (or removed) before the next frame
+    grid_lines_changed: bool,
     screen_width: f32,
     screen_height: f32,
 
-    rng: ThreadRng,
+    rng: StdRng,
 }
 
 impl CityGrowScene {
+    /// Scene seeded from `config.seed`, or randomly if no seed is configured
     pub fn with_config(width: u32, height: u32, config: CityGrowSceneConfig) -> Self {
-        let cell_count_x = (width as f32 / config.scale / 2.0).round() as u32;
-        let cell_count_y = (height as f32 / config.scale / 2.0).round() as u32;
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_rng(&mut rand::rng()),
+        };
+        Self::with_config_and_rng(width, height, config, rng)
+    }
+
+    /// Scene whose whole simulation is driven by one generator seeded with `seed`;
+    /// the same seed, size and config always grow the same city
+    pub fn with_config_and_seed(
+        width: u32,
+        height: u32,
+        config: CityGrowSceneConfig,
+        seed: u64,
+    ) -> Self {
+        Self::with_config_and_rng(width, height, config, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_config_and_rng(
+        width: u32,
+        height: u32,
+        config: CityGrowSceneConfig,
+        rng: StdRng,
+    ) -> Self {
+        let (cell_count_x, cell_count_y) = Self::cell_counts(width, height, config.scale);
+        let base_scale = config.scale;
 
         let mut scene = Self {
-            grid: Grid::new(cell_count_x, cell_count_y),
+            grid: Grid::new(
+                cell_count_x,
+                cell_count_y,
+                config.wrap_edges,
+                config.territorial,
+                config.allow_diagonal,
+            ),
             branch_list: Vec::new(),
             config,
-            reverse_running: false,
+            phase: Phase::Growing,
             painter_state: PainterState {
                 draw_history: HashMap::new(),
                 main_branches: HashSet::new(),
+                spawn_order: Vec::new(),
+                cells: HashMap::new(),
+                retiring: None,
+                parents: HashMap::new(),
+                roads: HashMap::new(),
             },
             needs_initial_clear: true,
+            needs_replay: false,
+            action_cap_reached: false,
+            branch_cap_reached: false,
+            phase_changes: Vec::new(),
+            debug_grid: false,
+            overlay_format: None,
+            debug_overlay_format: None,
+            district_label_format: None,
+            debug_fps: 0.0,
+            growth_time_accumulator: 0.0,
+            quality_level: MAX_QUALITY_LEVEL,
+            paused: false,
+            base_scale,
+            dpi: 96,
+            background_changed: false,
+            completed_cycles: 0,
+            finished: false,
+            palette_index: None,
+            idle_until: None,
+            idle_forever: false,
+            grid_lines_changed: true,
 
             screen_width: width as f32,
             screen_height: height as f32,
-            rng: rand::rng(),
+            rng,
         };
 
         scene.initialize(scene.config.start_branches as usize);
         scene
     }
 
-    fn initialize(&mut self, start_branches: usize) {
-        self.initialize_with_clear(start_branches, true);
+    /// Grow a complete city at the given size and write it to a PNG file,
+    /// e.g. as the wallpaper preview image shown by Lively
+    pub fn save_thumbnail(
+        config: &CityGrowSceneConfig,
+        width: u32,
+        height: u32,
+        path: &Path,
+    ) -> Result<()> {
+        Self::render_offscreen(config, width, height, OFFSCREEN_MAX_STEPS)?.save_png(path)?;
+        info!("Thumbnail written to {}", path.display());
+        Ok(())
     }
 
-    fn initialize_with_clear(&mut self, start_branches: usize, clear: bool) {
-        self.grid.fill(false);
-        self.branch_list.clear();
-        self.reverse_running = false;
-        self.painter_state.draw_history.clear();
-        self.painter_state.main_branches.clear();
-        self.needs_initial_clear = clear;
+    /// Grow a city for `steps` growth steps at the given size and return it as PNG bytes,
+    /// e.g. for a small library preview. Uses the same fixed seed as `save_thumbnail`.
+    #[allow(dead_code)]
+    pub fn render_preview(
+        config: &CityGrowSceneConfig,
+        width: u32,
+        height: u32,
+        steps: u32,
+    ) -> Result<Vec<u8>> {
+        Self::render_offscreen(config, width, height, steps as usize)?.png_bytes()
+    }
 
-        self.branch_list = (0..start_branches)
-            .map(|_| {
-                let pos = self.grid.random_pos(&mut self.rng);
-                let branch = Branch::new(pos, &self.config, &mut self.rng);
-                self.grid.set(pos.x as u32, pos.y as u32, true);
-                self.painter_state.main_branches.insert(branch.id);
-                debug!("Branch initialized at ({}, {})", pos.x, pos.y);
-                branch
-            })
-            .collect();
-        debug!("Initialized {} branches", start_branches);
+    /// Grow a complete city at the given size and write its drawing commands to a file as
+    /// newline-delimited JSON, see `export_ndjson` for the format
+    pub fn save_ndjson(
+        config: &CityGrowSceneConfig,
+        width: u32,
+        height: u32,
+        path: &Path,
+    ) -> Result<()> {
+        let mut scene = Self::with_config(width, height, config.clone());
+        scene.grow_to_completion();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        scene.export_ndjson(&mut writer)?;
+        writer.flush()?;
+        info!("Drawing commands written to {}", path.display());
+        Ok(())
     }
 
-    fn process_branching(&mut self) -> Vec<Event> {
-        let mut events = Vec::new();
-        let branch_count = self.branch_list.len();
-        let mut i = 0;
+    /// Grow a complete city at the given size and write it to an SVG file, see `export_svg`
+    pub fn save_svg(
+        config: &CityGrowSceneConfig,
+        width: u32,
+        height: u32,
+        path: &Path,
+    ) -> Result<()> {
+        let mut scene = Self::with_config(width, height, config.clone());
+        scene.grow_to_completion();
+        scene.export_svg(path)
+    }
 
-        while i < self.branch_list.len() {
-            let branch = self.branch_list.swap_remove(i);
+    /// Write the recorded history as an SVG image with the scene size as its viewBox
+    ///
+    /// Branches are layered like on screen, non-main branches below main branches. The colors
+    /// are the untinted ones from the history.
+    pub fn export_svg(&self, path: &Path) -> Result<()> {
+        let (main, non_main): (Vec<u32>, Vec<u32>) = self
+            .painter_state
+            .spawn_order
+            .iter()
+            .partition(|branch_id| self.painter_state.main_branches.contains(branch_id));
+        let operations = non_main
+            .iter()
+            .chain(&main)
+            .filter_map(|branch_id| self.painter_state.draw_history.get(branch_id))
+            .flatten();
+
+        let mut writer = BufWriter::new(
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+        );
+        svg::write_svg(
+            &mut writer,
+            self.screen_width as u32,
+            self.screen_height as u32,
+            operations,
+        )?;
+        writer.flush()?;
+        info!("SVG written to {}", path.display());
+        Ok(())
+    }
+
+    /// Write everything needed to continue this city later to a file, see `load_state`
+    pub fn save_state(&self, path: &Path) -> Result<()> {
+        let state = SavedState {
+            version: STATE_FORMAT_VERSION,
+            width: self.screen_width as u32,
+            height: self.screen_height as u32,
+            grid_size: self.grid_size(),
+            phase: self.phase,
+            branches: self.branch_list.clone(),
+            main_branches: self.painter_state.main_branches.clone(),
+            spawn_order: self.painter_state.spawn_order.clone(),
+            cells: self.painter_state.cells.clone(),
+            retiring: self.painter_state.retiring,
+            parents: self.painter_state.parents.clone(),
+            roads: self.painter_state.roads.clone(),
+            history: self
+                .painter_state
+                .spawn_order
+                .iter()
+                .filter_map(|branch_id| {
+                    let history = self.painter_state.draw_history.get(branch_id)?;
+                    Some(
+                        history
+                            .iter()
+                            .map(|operation| ExportEntry::new(*branch_id, operation)),
+                    )
+                })
+                .flatten()
+                .collect(),
+        };
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &state)?;
+        writer.flush()?;
+        info!("Scene state written to {}", path.display());
+        Ok(())
+    }
+
+    /// Continue a city saved by `save_state`, at the screen size it was saved with
+    ///
+    /// Everything recorded is redrawn on the first frame. The random generator is not part of
+    /// the state, so the city grows on differently than it would have without the restart.
+    pub fn load_state(path: &Path, config: CityGrowSceneConfig) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_reader(BufReader::new(File::open(path)?))
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let version = value.get("version").and_then(serde_json::Value::as_u64);
+        if version != Some(STATE_FORMAT_VERSION as u64) {
+            bail!(
+                "{} has state format version {:?}, expected {}",
+                path.display(),
+                version,
+                STATE_FORMAT_VERSION
+            );
+        }
+        let state = SavedState::deserialize(value)
+            .with_context(|| format!("Invalid scene state in {}", path.display()))?;
+
+        let mut scene = Self::with_config(state.width, state.height, config);
+        if scene.grid_size() != state.grid_size {
+            bail!(
+                "{} was saved for a {}x{} grid, the configuration gives {}x{}",
+                path.display(),
+                state.grid_size.0,
+                state.grid_size.1,
+                scene.grid_size().0,
+                scene.grid_size().1
+            );
+        }
+
+        // Districts of finished branches follow from the branch-off links to their start branch
+        let district = |branch_id: u32| {
+            let mut id = branch_id;
+            while let Some(&parent) = state.parents.get(&id) {
+                id = parent;
+            }
+            state
+                .branches
+                .iter()
+                .find(|branch| branch.id == branch_id)
+                .map_or(id, |branch| branch.district)
+        };
+        scene.grid.fill(false);
+        for (&branch_id, cells) in &state.cells {
+            let district = district(branch_id);
+            for pos in cells {
+                scene.grid.occupy(*pos, district);
+            }
+        }
+        scene.branch_list = state.branches;
+        for branch in &mut scene.branch_list {
+            branch.reopen_fields();
+        }
+        scene.phase = state.phase;
+        scene.painter_state.draw_history.clear();
+        for entry in &state.history {
+            if let Some(operation) = entry.to_operation() {
+                scene
+                    .painter_state
+                    .draw_history
+                    .entry(entry.branch())
+                    .or_default()
+                    .push(operation);
+            }
+        }
+        scene.painter_state.main_branches = state.main_branches;
+        scene.painter_state.spawn_order = state.spawn_order;
+        scene.painter_state.cells = state.cells;
+        scene.painter_state.retiring = state.retiring;
+        scene.painter_state.parents = state.parents;
+        scene.painter_state.roads = state.roads;
+        scene.needs_replay = true;
+
+        info!(
+            "Scene state restored from {} ({} branches)",
+            path.display(),
+            scene.branch_list.len()
+        );
+        Ok(scene)
+    }
+
+    /// Redraw the recorded history, non-main branches below main branches
+    fn replay_history(&self, renderer: &mut Renderer) -> Result<()> {
+        let (main, non_main): (Vec<u32>, Vec<u32>) = self
+            .painter_state
+            .spawn_order
+            .iter()
+            .partition(|branch_id| self.painter_state.main_branches.contains(branch_id));
+        let tint = self.road_tint();
+        for branch_id in non_main.iter().chain(&main) {
+            if let Some(history) = self.painter_state.draw_history.get(branch_id) {
+                match tint {
+                    Some(tint) => renderer.draw_batch(&Self::tinted(history.clone(), tint))?,
+                    None => renderer.draw_batch(history)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stream the recorded drawing commands as one JSON object per line
+    ///
+    /// Works on a finished city as well as on one that is still growing. Branches are written
+    /// in the order they spawned, each with its commands in drawing order. Every object has a
+    /// `type` ("line", "rect", "polyline", "bezier_path", ...), the `branch` id, its geometry in
+    /// pixels and `color` as `[r, g, b, a]` in 0..1, e.g.
+    /// `{"type":"line","branch":7,"x1":1.0,"y1":1.0,"x2":5.0,"y2":1.0,"width":2.0,"color":[...]}`
+    pub fn export_ndjson(&self, writer: &mut impl Write) -> Result<()> {
+        for branch_id in &self.painter_state.spawn_order {
+            let Some(history) = self.painter_state.draw_history.get(branch_id) else {
+                continue;
+            };
+            for operation in history {
+                serde_json::to_writer(&mut *writer, &ExportEntry::new(*branch_id, operation))?;
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the growth until every branch has stopped, returning all operations in frame order
+    /// (non-main branches of a step below its main branches)
+    fn grow_to_completion(&mut self) -> Vec<DrawOperation> {
+        self.grow_steps(OFFSCREEN_MAX_STEPS)
+    }
+
+    /// Run at most `max_steps` growth steps, stopping early once every branch has stopped
+    fn grow_steps(&mut self, max_steps: usize) -> Vec<DrawOperation> {
+        let mut operations = Vec::new();
+        let mut steps = 0;
+        while !self.branch_list.is_empty() && steps < max_steps {
+            let (non_main_operations, main_operations) = self.grow_step();
+            operations.extend(non_main_operations);
+            operations.extend(main_operations);
+            steps += 1;
+        }
+        debug!(
+            "Grew city in {} steps ({} operations)",
+            steps,
+            operations.len()
+        );
+        operations
+    }
+
+    fn render_offscreen(
+        config: &CityGrowSceneConfig,
+        width: u32,
+        height: u32,
+        max_steps: usize,
+    ) -> Result<OffscreenRenderer> {
+        // Fixed seed (unless configured), so the preview stays the same between runs
+        let seed = config.seed.unwrap_or(THUMBNAIL_SEED);
+        let mut scene = Self::with_config_and_seed(width, height, config.clone(), seed);
+        let operations = scene.grow_steps(max_steps);
+
+        let renderer = OffscreenRenderer::new(width, height)?;
+        renderer.draw(&operations)?;
+        Ok(renderer)
+    }
+
+    /// Draw occupied cells and active branch positions as an overlay on top of the scene
+    pub fn with_debug_grid(mut self, enabled: bool) -> Self {
+        self.debug_grid = enabled;
+        self
+    }
+
+    /// Grid dimensions in cells
+    pub fn grid_size(&self) -> (u32, u32) {
+        (self.grid.size_x, self.grid.size_y)
+    }
+
+    /// Whether the cell at the given grid position is occupied, None if out of bounds
+    pub fn is_occupied(&self, x: u32, y: u32) -> Option<bool> {
+        self.grid.get(x, y)
+    }
+
+    /// Grid cell containing the screen point (`x`, `y`) in pixels; points outside the screen
+    /// give cells outside the grid
+    #[allow(dead_code)]
+    pub fn screen_to_grid(&self, x: f32, y: f32) -> Pos {
+        let cell_size = 2.0 * self.config.scale;
+        Pos::new(
+            (x / cell_size).floor() as i32,
+            (y / cell_size).floor() as i32,
+        )
+    }
+
+    /// Start a main branch with the given hue (in degrees) at a free grid cell, e.g. to grow a
+    /// city from a predefined outline. Returns false if the cell is occupied or outside the grid.
+    #[allow(dead_code)]
+    pub fn seed_branch(&mut self, grid_x: i32, grid_y: i32, hue: f32) -> bool {
+        let (Ok(x), Ok(y)) = (u32::try_from(grid_x), u32::try_from(grid_y)) else {
+            return false;
+        };
+        if self.grid.get(x, y) != Some(false) {
+            return false;
+        }
+
+        let pos = Pos::new(grid_x, grid_y);
+        let branch = Branch {
+            color: Hsla::new(
+                (hue.rem_euclid(360.0) / 360.0 * 256.0) as u8,
+                self.config.saturation_main,
+                self.config.lightness_default,
+                255,
+            ),
+            ..Branch::new(pos, &self.config, self.palette_index(), &mut self.rng)
+        };
+        self.grid.occupy(pos, branch.district);
+        self.painter_state.main_branches.insert(branch.id);
+        self.painter_state.cells.insert(branch.id, vec![pos]);
+        debug!("Branch seeded at ({}, {})", pos.x, pos.y);
+        self.branch_list.push(branch);
+        true
+    }
+
+    /// Every road step grown so far as a pair of grid cells, branch by branch in the order
+    /// they spawned. Branch-offs start at the cell of their parent, so the pairs form one
+    /// connected network per start branch. With `wrap_edges` a pair may join opposite edges.
+    #[allow(dead_code)]
+    pub fn grid_roads(&self) -> impl Iterator<Item = (Pos, Pos)> + '_ {
+        self.painter_state
+            .spawn_order
+            .iter()
+            .filter_map(|branch_id| self.painter_state.roads.get(branch_id))
+            .flatten()
+            .copied()
+    }
+
+    /// `grid_roads` as straight segments between cell centers in screen pixels. A step across
+    /// a wrapped edge ends outside the screen, next to where it started.
+    #[allow(dead_code)]
+    pub fn roads(&self) -> impl Iterator<Item = ((f32, f32), (f32, f32))> + '_ {
+        self.grid_roads().map(|(from, to)| {
+            let start = self.grid_to_screen(from);
+            let end = self.grid_to_screen(self.grid.nearest_image(to, from));
+            ((start.X, start.Y), (end.X, end.Y))
+        })
+    }
+
+    /// Fraction of the grid cells occupied by roads, in 0..1
+    pub fn occupancy(&self) -> f32 {
+        self.grid.data.count_ones() as f32 / self.grid.data.len().max(1) as f32
+    }
+
+    /// Occupancy of `bins_x` x `bins_y` equally sized regions of the grid, row by row
+    ///
+    /// The bin counts are clamped to the grid size, so every bin covers at least one cell.
+    #[allow(dead_code)]
+    pub fn density_grid(&self, bins_x: u32, bins_y: u32) -> Vec<f32> {
+        let (size_x, size_y) = self.grid_size();
+        let bins_x = bins_x.clamp(1, size_x);
+        let bins_y = bins_y.clamp(1, size_y);
+        let bin_index =
+            |x: u32, y: u32| (y * bins_y / size_y * bins_x + x * bins_x / size_x) as usize;
+
+        let mut occupied = vec![0u32; (bins_x * bins_y) as usize];
+        let mut total = vec![0u32; occupied.len()];
+        for y in 0..size_y {
+            for x in 0..size_x {
+                let bin = bin_index(x, y);
+                total[bin] += 1;
+                if self.grid.get(x, y) == Some(true) {
+                    occupied[bin] += 1;
+                }
+            }
+        }
+        occupied
+            .iter()
+            .zip(&total)
+            .map(|(&occupied, &total)| occupied as f32 / total as f32)
+            .collect()
+    }
+
+    /// Number of grid cells along each axis for the given screen size
+    ///
+    /// Both axes are derived the same way, so portrait and landscape screens get the same
+    /// cell density. Each axis keeps at least one cell so tiny or minimized windows
+    /// don't collapse the grid.
+    fn cell_counts(width: u32, height: u32, scale: f32) -> (u32, u32) {
+        let cells = |length: u32| ((length as f32 / scale / 2.0).round() as u32).max(1);
+        (cells(width), cells(height))
+    }
+
+    fn initialize(&mut self, start_branches: usize) {
+        self.initialize_with_clear(start_branches, true);
+    }
+
+    fn initialize_with_clear(&mut self, start_branches: usize, clear: bool) {
+        self.grid.fill(false);
+        self.branch_list.clear();
+        self.phase = Phase::Growing;
+        self.idle_until = None;
+        self.idle_forever = false;
+        // Every city gets the next palette
+        if !self.config.palettes.is_empty() {
+            let index =
+                self.palette_index.map_or(0, |index| index + 1) % self.config.palettes.len();
+            debug!("Using palette {:?}", self.config.palettes[index].name);
+            self.palette_index = Some(index);
+        }
+        self.painter_state.draw_history.clear();
+        self.painter_state.main_branches.clear();
+        self.painter_state.spawn_order.clear();
+        self.painter_state.cells.clear();
+        self.painter_state.retiring = None;
+        self.painter_state.parents.clear();
+        self.painter_state.roads.clear();
+        self.needs_initial_clear = clear;
+        self.action_cap_reached = false;
+        self.branch_cap_reached = false;
+        self.needs_replay = false;
+        self.growth_time_accumulator = 0.0;
+        self.phase_changes.push(AnimationPhase::Growing);
+
+        let free_cells = self.grid.free_cell_count();
+        let start_branches = if start_branches > free_cells {
+            info!(
+                "Grid only has {} free cells, starting {} branches instead of {}",
+                free_cells, free_cells, start_branches
+            );
+            free_cells
+        } else {
+            start_branches
+        };
+
+        // Each start branch gets its own free cell
+        self.branch_list = (0..start_branches)
+            .filter_map(|index| {
+                let pos = self
+                    .start_position(index, start_branches)
+                    .filter(|pos| self.grid.get(pos.x as u32, pos.y as u32) == Some(false))
+                    .or_else(|| self.grid.random_free_pos(&mut self.rng))?;
+                let branch = Branch::new(pos, &self.config, self.palette_index(), &mut self.rng);
+                self.grid.occupy(pos, branch.district);
+                self.painter_state.main_branches.insert(branch.id);
+                self.painter_state.cells.insert(branch.id, vec![pos]);
+                debug!("Branch initialized at ({}, {})", pos.x, pos.y);
+                Some(branch)
+            })
+            .collect();
+        debug!("Initialized {} branches", self.branch_list.len());
+    }
+
+    /// Cell of start branch `index` out of `count` according to `start_placement`, None to
+    /// pick a random free cell
+    fn start_position(&self, index: usize, count: usize) -> Option<Pos> {
+        let (size_x, size_y) = self.grid_size();
+        let (max_x, max_y) = (size_x as i32 - 1, size_y as i32 - 1);
+        match self.config.start_placement {
+            StartPlacement::Random => None,
+            StartPlacement::Center => {
+                let (center_x, center_y) = (size_x as f32 / 2.0, size_y as f32 / 2.0);
+                let radius = if count > 1 {
+                    size_x.min(size_y) as f32 / 8.0
+                } else {
+                    0.0
+                };
+                let angle = index as f32 / count as f32 * std::f32::consts::TAU;
+                Some(Pos::new(
+                    ((center_x + radius * angle.cos()) as i32).clamp(0, max_x),
+                    ((center_y + radius * angle.sin()) as i32).clamp(0, max_y),
+                ))
+            }
+            StartPlacement::Corners => [
+                Pos::new(0, 0),
+                Pos::new(max_x, 0),
+                Pos::new(0, max_y),
+                Pos::new(max_x, max_y),
+            ]
+            .get(index)
+            .copied(),
+            StartPlacement::Grid => {
+                let columns = (count as f32).sqrt().ceil() as usize;
+                let rows = count.div_ceil(columns);
+                let (column, row) = (index % columns, index / columns);
+                Some(Pos::new(
+                    ((column as f32 + 0.5) * size_x as f32 / columns as f32) as i32,
+                    ((row as f32 + 0.5) * size_y as f32 / rows as f32) as i32,
+                ))
+            }
+        }
+    }
+
+    fn process_branching(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        let branch_count = self.branch_list.len();
+        let mut i = 0;
+
+        while i < self.branch_list.len() {
+            let branch = self.branch_list.swap_remove(i);
+            // The removed branch still counts as active
+            let at_cap = self.at_branch_cap(self.branch_list.len() + 1);
             let scaled_chance = self.config.branch_chance(branch.mode)
+                * self
+                    .config
+                    .branch_age_multiplier(branch.age, branch.life_time)
                 * (1.0 + self.config.branch_fall_off)
                 / (self.config.branch_fall_off + branch_count as f32);
 
-            if self.rng.random::<f32>() < scaled_chance {
+            if !at_cap && self.rng.random::<f32>() < scaled_chance {
                 match branch.try_branch_off(&self.grid, &self.config, &mut self.rng) {
                     BranchOffResult::Success {
                         new_parent,
@@ -554,27 +2353,35 @@ impl CityGrowScene {
                         pos,
                         event,
                     } => {
-                        self.grid.set(pos.x as u32, pos.y as u32, true);
+                        self.grid.occupy(pos, child.district);
                         events.push(event);
 
-                        let child =
-                            if self.rng.random::<f32>() < self.config.prop_branch_off_to_main {
-                                let promoted_child = Branch {
-                                    color: Hsla::new(
+                        let child = if self.rng.random::<f32>()
+                            < self.config.prop_branch_off_to_main
+                        {
+                            let color = self
+                                .config
+                                .palette_color(self.palette_index(), &mut self.rng)
+                                .unwrap_or_else(|| {
+                                    Hsla::new(
                                         ((child.color.h + self.config.change_hue_new_main) as u16
                                             % 256) as u8,
                                         self.config.saturation_main,
                                         self.config.lightness_default,
                                         255,
-                                    ),
-                                    life_time: self.config.life_time,
-                                    ..child
-                                };
-                                self.painter_state.main_branches.insert(promoted_child.id);
-                                promoted_child
-                            } else {
-                                child
+                                    )
+                                });
+                            let promoted_child = Branch {
+                                color,
+                                life_time: self.config.life_time,
+                                thickness: self.config.main_thickness,
+                                ..child
                             };
+                            self.painter_state.main_branches.insert(promoted_child.id);
+                            promoted_child
+                        } else {
+                            child
+                        };
 
                         self.branch_list.push(child);
                         self.branch_list.push(new_parent);
@@ -606,16 +2413,26 @@ impl CityGrowScene {
 
         while i < self.branch_list.len() {
             let branch = self.branch_list.swap_remove(i);
+            let branch = if branch.age >= branch.life_time {
+                match self.handle_life_end(branch, &mut events) {
+                    Some(branch) => branch,
+                    None => continue,
+                }
+            } else {
+                branch
+            };
+
             if let Some((new_branch, pos, next_pos, own_fields_tip)) =
                 branch.step_branch(&self.grid, &self.config, &mut self.rng)
             {
-                self.grid.set(next_pos.x as u32, next_pos.y as u32, true);
+                self.grid.occupy(next_pos, new_branch.district);
                 events.push(Event::Move {
                     branch_id: new_branch.id,
                     from: pos,
                     to: next_pos,
                     mode: new_branch.mode,
                     color: new_branch.color,
+                    thickness: new_branch.thickness,
                     own_fields_tip,
                 });
                 self.branch_list.push(new_branch);
@@ -628,6 +2445,45 @@ impl CityGrowScene {
         events
     }
 
+    /// Apply the configured life end behavior to a branch that reached its life time
+    /// Returns the branch if it keeps growing
+    fn handle_life_end(&mut self, branch: Branch, events: &mut Vec<Event>) -> Option<Branch> {
+        match self.config.on_life_end {
+            LifeEndBehavior::Stop => None,
+            LifeEndBehavior::ConvertToLand => {
+                branch.convert_to_land(&self.grid, &self.config, &mut self.rng)
+            }
+            // Children of a life end don't spawn children of their own when they end
+            LifeEndBehavior::SpawnChildren if branch.life_end_child => None,
+            LifeEndBehavior::SpawnChildren => {
+                let mut parent = branch;
+                for _ in 0..LIFE_END_CHILDREN {
+                    if self.at_branch_cap(self.branch_list.len()) {
+                        break;
+                    }
+                    match parent.try_branch_off(&self.grid, &self.config, &mut self.rng) {
+                        BranchOffResult::Success {
+                            new_parent,
+                            child,
+                            pos,
+                            event,
+                        } => {
+                            self.grid.occupy(pos, child.district);
+                            events.push(event);
+                            self.branch_list.push(Branch {
+                                life_end_child: true,
+                                ..child
+                            });
+                            parent = new_parent;
+                        }
+                        BranchOffResult::Failure { .. } => break,
+                    }
+                }
+                None
+            }
+        }
+    }
+
     /// Helper: Convert grid position to screen coordinates
     fn grid_to_screen(&self, pos: Pos) -> Vector2 {
         Vector2 {
@@ -651,28 +2507,213 @@ impl CityGrowScene {
             to_pos.x.min(imaginary_point.x),
             to_pos.y.min(imaginary_point.y),
         );
+        // Inset on every side so the road stays centered in the gap between blocks
+        let margin = self.config.block_margin.clamp(0.0, self.config.scale / 2.0);
         D2D_RECT_F {
-            left: corner.x as f32 * 2.0 * self.config.scale + self.config.scale,
-            top: corner.y as f32 * 2.0 * self.config.scale + self.config.scale,
+            left: corner.x as f32 * 2.0 * self.config.scale + self.config.scale + margin,
+            top: corner.y as f32 * 2.0 * self.config.scale + self.config.scale + margin,
             right: corner.x as f32 * 2.0 * self.config.scale
                 + self.config.scale
-                + (2.0 * self.config.scale - self.config.scale),
+                + (2.0 * self.config.scale - self.config.scale)
+                - margin,
             bottom: corner.y as f32 * 2.0 * self.config.scale
                 + self.config.scale
-                + (2.0 * self.config.scale - self.config.scale),
+                + (2.0 * self.config.scale - self.config.scale)
+                - margin,
+        }
+    }
+
+    /// Helper: Create the fill operation for a city block, rounding its corners if configured
+    fn city_block(&self, rect: D2D_RECT_F, color: D2D1_COLOR_F) -> DrawOperation {
+        if let Some(top_alpha) = self.config.block_gradient_top_alpha {
+            // One gradient over the whole screen height, shared by every block
+            return DrawOperation::gradient_rect(
+                rect,
+                vec![(0.0, color), (1.0, color.with_alpha(top_alpha))],
+                Vector2 {
+                    X: 0.0,
+                    Y: self.screen_height,
+                },
+                Vector2 { X: 0.0, Y: 0.0 },
+            );
+        }
+        let radius = self.config.block_corner_radius;
+        if radius > 0.0 {
+            DrawOperation::filled_rounded_rect(rect, radius, radius, color)
+        } else {
+            DrawOperation::filled_rect(rect, color)
+        }
+    }
+
+    /// Advance all branches by one step, recording the resulting operations in the history
+    ///
+    /// Returns the operations of non-main and main branches separately, so main branches can
+    /// be drawn last (on top).
+    fn grow_step(&mut self) -> (Vec<DrawOperation>, Vec<DrawOperation>) {
+        // Branches that stop during this step get their curved road tips completed
+        let growing_ids: Vec<u32> = if self.config.curved_roads {
+            self.branch_list.iter().map(|branch| branch.id).collect()
+        } else {
+            Vec::new()
+        };
+
+        // Generate events for this step
+        let events = {
+            let mut events = self.process_branching();
+            events.extend(self.process_stepping());
+            events
+        };
+
+        // Separate events into non-main and main branch events for proper layering
+        // Non-main branches are drawn first (appear below), main branches last (appear on top)
+        let mut non_main_operations = Vec::new();
+        let mut main_operations = Vec::new();
+
+        for event in events {
+            let (branch_id, operations) = self.event_to_draw_operations(&event);
+
+            match event {
+                Event::BranchOff {
+                    child_id,
+                    parent_id,
+                    parent_pos,
+                    child_pos,
+                    ..
+                } => {
+                    self.painter_state
+                        .roads
+                        .entry(child_id)
+                        .or_default()
+                        .push((parent_pos, child_pos));
+                    self.painter_state.parents.insert(child_id, parent_id);
+                    self.painter_state
+                        .cells
+                        .entry(child_id)
+                        .or_default()
+                        .push(child_pos);
+                }
+                Event::Move {
+                    branch_id,
+                    from,
+                    to,
+                    ..
+                } => {
+                    self.painter_state
+                        .roads
+                        .entry(branch_id)
+                        .or_default()
+                        .push((from, to));
+                    self.painter_state
+                        .cells
+                        .entry(branch_id)
+                        .or_default()
+                        .push(to);
+                }
+            }
+
+            self.record_operations(
+                branch_id,
+                operations,
+                &mut non_main_operations,
+                &mut main_operations,
+            );
+        }
+
+        // Stop growing at the cap, the city then holds and reverses as usual
+        if self.config.completion != CompletionMode::Continuous
+            && !self.branch_list.is_empty()
+            && self.over_action_cap()
+        {
+            self.branch_list.clear();
+        }
+
+        for branch_id in growing_ids {
+            if self.branch_list.iter().any(|branch| branch.id == branch_id) {
+                continue;
+            }
+            if let Some(tip) = self.curved_road_tip(branch_id) {
+                self.record_operations(
+                    branch_id,
+                    vec![tip],
+                    &mut non_main_operations,
+                    &mut main_operations,
+                );
+            }
+        }
+
+        (non_main_operations, main_operations)
+    }
+
+    /// Store a branch's operations in the history for the reverse animation and add them to
+    /// the main or non-main operations of this step
+    fn record_operations(
+        &mut self,
+        branch_id: u32,
+        operations: Vec<DrawOperation>,
+        non_main_operations: &mut Vec<DrawOperation>,
+        main_operations: &mut Vec<DrawOperation>,
+    ) {
+        if !self.painter_state.draw_history.contains_key(&branch_id) {
+            self.painter_state.spawn_order.push(branch_id);
         }
+        let branch_history = self
+            .painter_state
+            .draw_history
+            .entry(branch_id)
+            .or_default();
+        branch_history.extend(operations.iter().cloned());
+
+        // Separate by main/non-main for layering
+        if self.painter_state.main_branches.contains(&branch_id) {
+            main_operations.extend(operations);
+        } else {
+            non_main_operations.extend(operations);
+        }
+    }
+
+    /// Straight line over the last half cell of a stopped branch, which curved roads leave
+    /// open while the branch might still turn
+    fn curved_road_tip(&self, branch_id: u32) -> Option<DrawOperation> {
+        let cells = self.painter_state.cells.get(&branch_id)?;
+        let [.., previous, last] = cells.as_slice() else {
+            return None;
+        };
+        let (color, thickness) = self
+            .painter_state
+            .draw_history
+            .get(&branch_id)?
+            .iter()
+            .rev()
+            .find_map(|operation| match operation {
+                DrawOperation::Line {
+                    color, thickness, ..
+                }
+                | DrawOperation::BezierPath {
+                    color, thickness, ..
+                } => Some((*color, *thickness)),
+                _ => None,
+            })?;
+        let screen_last = self.grid_to_screen(*last);
+        let previous = self.grid.nearest_image(*previous, *last);
+        Some(DrawOperation::line(
+            midpoint(self.grid_to_screen(previous), screen_last),
+            screen_last,
+            color,
+            thickness,
+        ))
     }
 
     /// Convert a move event into draw operations (line + optional fill rectangles for city mode)
     /// Returns operations to draw and store in history
     fn event_to_draw_operations(&mut self, event: &Event) -> (u32, Vec<DrawOperation>) {
-        let (branch_id, from_pos, to_pos, mode, color, own_fields_tip) = match event {
+        let (branch_id, from_pos, to_pos, mode, color, thickness, own_fields_tip) = match event {
             Event::Move {
                 branch_id,
                 from: from_pos,
                 to: to_pos,
                 mode,
                 color,
+                thickness,
                 own_fields_tip,
             } => (
                 *branch_id,
@@ -680,6 +2721,7 @@ impl CityGrowScene {
                 *to_pos,
                 *mode,
                 *color,
+                *thickness,
                 *own_fields_tip,
             ),
             Event::BranchOff {
@@ -688,18 +2730,26 @@ impl CityGrowScene {
                 child_pos,
                 parent_mode,
                 child_color,
+                child_thickness,
+                ..
             } => (
                 *child_id,
                 *parent_pos,
                 *child_pos,
                 *parent_mode,
                 *child_color,
+                *child_thickness,
                 *parent_pos,
             ),
         };
+        let thickness = thickness * self.config.scale;
 
+        // A step across a wrapped edge is drawn towards the copy of `to` next to `from` and
+        // from the copy of `from` next to `to`, both clipped at the screen edge
+        let unwrapped_to = self.grid.nearest_image(to_pos, from_pos);
+        let wrapped = unwrapped_to != to_pos;
         let screen_from = self.grid_to_screen(from_pos);
-        let screen_to = self.grid_to_screen(to_pos);
+        let screen_to = self.grid_to_screen(unwrapped_to);
         let d2d_color = color.to_d2d_color();
 
         let mut operations = Vec::new();
@@ -707,37 +2757,109 @@ impl CityGrowScene {
         // Add fill rectangles for city mode
         if mode == BranchMode::City {
             // Calculate direction of the line being drawn
-            let direction = Pos::new(to_pos.x - from_pos.x, to_pos.y - from_pos.y);
+            let direction = Pos::new(unwrapped_to.x - from_pos.x, unwrapped_to.y - from_pos.y);
 
             // Perpendicular is 90-degree rotation: (-dy, dx)
             let perpendicular = Pos::new(-direction.y, direction.x);
 
-            // Only draw rectangles if there's actual movement
-            if perpendicular.x != 0 || perpendicular.y != 0 {
-                let rect1 = self.compute_fill_rect(own_fields_tip, to_pos, perpendicular);
+            // Only draw rectangles if there's actual movement, and only beside straight steps
+            // (blocks beside a diagonal would cover the cells next to it)
+            let straight = direction.x == 0 || direction.y == 0;
+            if straight && (perpendicular.x != 0 || perpendicular.y != 0) {
+                let rect1 = self.compute_fill_rect(own_fields_tip, unwrapped_to, perpendicular);
                 let rect2 = self.compute_fill_rect(
                     own_fields_tip,
-                    to_pos,
+                    unwrapped_to,
                     Pos::new(-perpendicular.x, -perpendicular.y),
                 );
 
                 let fade_color = d2d_color.with_alpha(self.config.city_rect_alpha);
-                operations.push(DrawOperation::filled_rect(rect1, fade_color));
-                operations.push(DrawOperation::filled_rect(rect2, fade_color));
+                operations.extend(
+                    [rect1, rect2]
+                        .into_iter()
+                        .map(|rect| self.city_block(rect, fade_color)),
+                );
             }
         }
 
-        // Add the line
-        operations.push(DrawOperation::line(
-            screen_from,
-            screen_to,
-            d2d_color,
-            self.config.scale,
-        ));
+        // Add the line, highways get their own shade if enabled
+        let line_color = if self.config.distinguish_modes && mode == BranchMode::Land {
+            color
+                .shift_lightness(self.config.land_color_shift)
+                .to_d2d_color()
+        } else {
+            d2d_color
+        };
+        if self.config.curved_roads {
+            operations.push(self.curved_road_segment(
+                event,
+                screen_from,
+                screen_to,
+                line_color,
+                thickness,
+            ));
+        } else {
+            operations.push(DrawOperation::line(
+                screen_from,
+                screen_to,
+                line_color,
+                thickness,
+            ));
+            if wrapped {
+                operations.push(DrawOperation::line(
+                    self.grid_to_screen(self.grid.nearest_image(from_pos, to_pos)),
+                    self.grid_to_screen(to_pos),
+                    line_color,
+                    thickness,
+                ));
+            }
+        }
 
         (branch_id, operations)
     }
 
+    /// Curved road piece for a step from `screen_from` to `screen_to`: from the middle of the
+    /// branch's previous step, around `screen_from`, to the middle of this step
+    ///
+    /// A branch's first step (and a branch off) has no previous step and starts straight.
+    fn curved_road_segment(
+        &self,
+        event: &Event,
+        screen_from: Vector2,
+        screen_to: Vector2,
+        color: D2D1_COLOR_F,
+        thickness: f32,
+    ) -> DrawOperation {
+        let half_way = midpoint(screen_from, screen_to);
+        // Cells of this branch are recorded after its operations, the last one is `from`
+        let previous =
+            match event {
+                Event::Move {
+                    branch_id, from, ..
+                } => self.painter_state.cells.get(branch_id).and_then(|cells| {
+                    match cells.as_slice() {
+                        [.., previous, last] if last == from => {
+                            Some(self.grid.nearest_image(*previous, *from))
+                        }
+                        _ => None,
+                    }
+                }),
+                Event::BranchOff { .. } => None,
+            };
+        match previous {
+            Some(previous) => DrawOperation::bezier_path(
+                vec![
+                    midpoint(self.grid_to_screen(previous), screen_from),
+                    screen_from,
+                    half_way,
+                ],
+                color,
+                thickness,
+            ),
+            None => DrawOperation::line(screen_from, half_way, color, thickness),
+        }
+    }
+
     /// Consolidate consecutive lines into polylines for more efficient rendering
     fn consolidate_lines(operations: &[DrawOperation]) -> Vec<DrawOperation> {
         if operations.is_empty() {
@@ -819,8 +2941,20 @@ impl CityGrowScene {
 
                     // Convert operation to black version for erasure
                     let black_op = match op {
-                        DrawOperation::FilledRect { rect, .. } => DrawOperation::FilledRect {
+                        DrawOperation::FilledRect { rect, .. }
+                        | DrawOperation::GradientRect { rect, .. } => DrawOperation::FilledRect {
+                            rect: *rect,
+                            color: D2D1_COLOR_F::black(),
+                        },
+                        DrawOperation::FilledRoundedRect {
+                            rect,
+                            radius_x,
+                            radius_y,
+                            ..
+                        } => DrawOperation::FilledRoundedRect {
                             rect: *rect,
+                            radius_x: *radius_x,
+                            radius_y: *radius_y,
                             color: D2D1_COLOR_F::black(),
                         },
                         DrawOperation::Rect {
@@ -837,6 +2971,43 @@ impl CityGrowScene {
                             color: D2D1_COLOR_F::black(),
                             thickness: *thickness,
                         },
+                        DrawOperation::BezierPath {
+                            points, thickness, ..
+                        } => DrawOperation::BezierPath {
+                            points: points.clone(),
+                            color: D2D1_COLOR_F::black(),
+                            thickness: *thickness,
+                        },
+                        DrawOperation::Ellipse {
+                            center,
+                            radius_x,
+                            radius_y,
+                            filled,
+                            thickness,
+                            ..
+                        } => DrawOperation::Ellipse {
+                            center: *center,
+                            radius_x: *radius_x,
+                            radius_y: *radius_y,
+                            color: D2D1_COLOR_F::black(),
+                            filled: *filled,
+                            thickness: *thickness,
+                        },
+                        DrawOperation::Arc {
+                            center,
+                            radius,
+                            start_angle,
+                            sweep_angle,
+                            thickness,
+                            ..
+                        } => DrawOperation::Arc {
+                            center: *center,
+                            radius: *radius,
+                            start_angle: *start_angle,
+                            sweep_angle: *sweep_angle,
+                            color: D2D1_COLOR_F::black(),
+                            thickness: *thickness,
+                        },
                         _ => continue,
                     };
                     result.push(black_op);
@@ -864,7 +3035,7 @@ impl CityGrowScene {
     }
 
     /// Batch erase operations in MIN blend mode for efficiency
-    fn batch_erase(&self, renderer: &Renderer, operations: &[DrawOperation]) -> Result<()> {
+    fn batch_erase(&self, renderer: &mut Renderer, operations: &[DrawOperation]) -> Result<()> {
         if operations.is_empty() {
             return Ok(());
         }
@@ -875,37 +3046,562 @@ impl CityGrowScene {
         // Set MIN blend mode once for all operations
         renderer.set_min_blend();
 
-        // Use batch drawing for efficiency
-        renderer.draw_batch(&optimized_ops)?;
+        // Rounded corners drawn on the finished city must be erased with the same stroke style,
+        // its flat caps keep the erase inside the cells being removed
+        renderer.set_round_joins(self.config.rounded_joins);
 
-        Ok(())
+        // Use batch drawing for efficiency
+        let result = renderer.draw_batch(&optimized_ops);
+        renderer.set_round_joins(false);
+        result
     }
 
-    /// Process reverse animation step
-    /// Non-main branches erase first, then main branches
-    fn reverse_step(&mut self, renderer: &Renderer) -> Result<bool> {
-        if self.painter_state.draw_history.is_empty() {
-            return Ok(true); // Done reversing
-        }
+    /// Join each branch's connected lines into polylines, keeping their original colors
+    /// Only chains with at least one corner are returned
+    fn history_polylines(history: &[DrawOperation]) -> Vec<DrawOperation> {
+        let mut polylines = Vec::new();
+        let mut points: Vec<Vector2> = Vec::new();
+        let mut style = None;
 
-        let (main_branch_ids, non_main_branch_ids): (Vec<u32>, Vec<u32>) = self
-            .painter_state
-            .draw_history
-            .keys()
-            .copied()
-            .partition(|branch_id| self.painter_state.main_branches.contains(branch_id));
+        let mut flush = |points: &mut Vec<Vector2>, style: Option<(D2D1_COLOR_F, f32)>| {
+            if let Some((color, thickness)) = style.filter(|_| points.len() > 2) {
+                polylines.push(DrawOperation::polyline(points.clone(), color, thickness));
+            }
+            points.clear();
+        };
 
-        // Decide which branches to process (non-main first, then main)
-        let branches_to_process = if !non_main_branch_ids.is_empty() {
+        for op in history {
+            let DrawOperation::Line {
+                start,
+                end,
+                color,
+                thickness,
+            } = op
+            else {
+                continue;
+            };
+
+            let connected = points.last().is_some_and(|last| {
+                (last.X - start.X).abs() < 0.01 && (last.Y - start.Y).abs() < 0.01
+            }) && style == Some((*color, *thickness));
+
+            if !connected {
+                flush(&mut points, style);
+                points.push(*start);
+                style = Some((*color, *thickness));
+            }
+            points.push(*end);
+        }
+        flush(&mut points, style);
+
+        polylines
+    }
+
+    /// Redraw the finished roads as polylines with round joins to smooth their corners
+    fn smooth_finished_roads(&self, renderer: &mut Renderer) -> Result<()> {
+        let mut polylines: Vec<DrawOperation> = self
+            .painter_state
+            .draw_history
+            .values()
+            .flat_map(|history| Self::history_polylines(history))
+            .collect();
+        if let Some(tint) = self.road_tint() {
+            polylines = Self::tinted(polylines, tint);
+        }
+
+        renderer.set_round_joins(true);
+        let result = renderer.draw_batch(&polylines);
+        renderer.set_round_joins(false);
+        result
+    }
+
+    /// Debug overlay: faint tint over occupied cells and a dot on each active branch position
+    fn debug_grid_operations(&self) -> Vec<DrawOperation> {
+        let cell_size = 2.0 * self.config.scale;
+        let tint = D2D1_COLOR_F {
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+            a: 0.15,
+        };
+        let dot = D2D1_COLOR_F {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        };
+
+        let mut operations = Vec::new();
+
+        // Merge runs of occupied cells in a row into a single rectangle
+        let (size_x, size_y) = self.grid_size();
+        for y in 0..size_y {
+            let mut run_start = None;
+            for x in 0..=size_x {
+                match (self.is_occupied(x, y) == Some(true), run_start) {
+                    (true, None) => run_start = Some(x),
+                    (false, Some(start)) => {
+                        operations.push(DrawOperation::filled_rect(
+                            D2D_RECT_F {
+                                left: start as f32 * cell_size,
+                                top: y as f32 * cell_size,
+                                right: x as f32 * cell_size,
+                                bottom: (y + 1) as f32 * cell_size,
+                            },
+                            tint,
+                        ));
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for branch in &self.branch_list {
+            let center = self.grid_to_screen(branch.pos);
+            operations.push(DrawOperation::filled_rect(
+                D2D_RECT_F {
+                    left: center.X - self.config.scale,
+                    top: center.Y - self.config.scale,
+                    right: center.X + self.config.scale,
+                    bottom: center.Y + self.config.scale,
+                },
+                dot,
+            ));
+        }
+
+        operations
+    }
+
+    /// Lines along every grid cell boundary, for `show_grid`
+    fn grid_line_operations(&self) -> Vec<DrawOperation> {
+        let cell_size = 2.0 * self.config.scale;
+        let color = D2D1_COLOR_F {
+            r: 0.5,
+            g: 0.5,
+            b: 0.5,
+            a: 0.25,
+        };
+        let (size_x, size_y) = self.grid_size();
+        let (width, height) = (size_x as f32 * cell_size, size_y as f32 * cell_size);
+
+        let vertical = (0..=size_x).map(|x| {
+            let x = x as f32 * cell_size;
+            DrawOperation::line(
+                Vector2 { X: x, Y: 0.0 },
+                Vector2 { X: x, Y: height },
+                color,
+                1.0,
+            )
+        });
+        let horizontal = (0..=size_y).map(|y| {
+            let y = y as f32 * cell_size;
+            DrawOperation::line(
+                Vector2 { X: 0.0, Y: y },
+                Vector2 { X: width, Y: y },
+                color,
+                1.0,
+            )
+        });
+        vertical.chain(horizontal).collect()
+    }
+
+    /// Configured overlay text laid out over the whole screen, minus the padding
+    fn text_overlay(&self) -> Option<TextOverlay> {
+        let text = self.config.overlay_text.clone()?;
+        let [r, g, b, a] = self.config.overlay_color;
+        Some(TextOverlay {
+            text,
+            format: self.overlay_format.clone()?,
+            rect: D2D_RECT_F {
+                left: OVERLAY_TEXT_PADDING,
+                top: OVERLAY_TEXT_PADDING,
+                right: (self.screen_width - OVERLAY_TEXT_PADDING).max(OVERLAY_TEXT_PADDING),
+                bottom: (self.screen_height - OVERLAY_TEXT_PADDING).max(OVERLAY_TEXT_PADDING),
+            },
+            color: D2D1_COLOR_F { r, g, b, a },
+        })
+    }
+
+    /// Name labels centered on the cells of every branch with at least
+    /// `district_label_min_cells` cells that is still on screen
+    fn district_label_operations(&self) -> Vec<DrawOperation> {
+        let names = &self.config.district_label_names;
+        let Some(format) = self
+            .district_label_format
+            .as_ref()
+            .filter(|_| !names.is_empty())
+        else {
+            return Vec::new();
+        };
+        let [r, g, b, a] = self.config.district_label_color;
+        let color = D2D1_COLOR_F { r, g, b, a };
+        let (width, height) = (
+            DISTRICT_LABEL_FONT_SIZE * 10.0,
+            DISTRICT_LABEL_FONT_SIZE * 2.0,
+        );
+        self.painter_state
+            .spawn_order
+            .iter()
+            .filter(|branch_id| self.painter_state.draw_history.contains_key(branch_id))
+            .filter_map(|branch_id| {
+                let cells = self.painter_state.cells.get(branch_id)?;
+                if cells.len() < self.config.district_label_min_cells as usize {
+                    return None;
+                }
+                let (sum_x, sum_y) = cells.iter().fold((0.0, 0.0), |(x, y), pos| {
+                    let center = self.grid_to_screen(*pos);
+                    (x + center.X, y + center.Y)
+                });
+                let (x, y) = (sum_x / cells.len() as f32, sum_y / cells.len() as f32);
+                Some(DrawOperation::text(
+                    names[*branch_id as usize % names.len()].clone(),
+                    format.clone(),
+                    D2D_RECT_F {
+                        left: x - width / 2.0,
+                        top: y - height / 2.0,
+                        right: x + width / 2.0,
+                        bottom: y + height / 2.0,
+                    },
+                    color,
+                ))
+            })
+            .collect()
+    }
+
+    /// Frame rate and branch counts in the top-left corner
+    fn debug_overlay_operation(&self) -> Option<DrawOperation> {
+        let active = self.branch_list.len();
+        // Branches that drew something but are no longer growing
+        let stopped = self.painter_state.draw_history.len().saturating_sub(active);
+        Some(DrawOperation::text(
+            format!(
+                "{:.0} fps\n{} active / {} stopped branches\n{:.0}% occupied",
+                self.debug_fps,
+                active,
+                stopped,
+                self.occupancy() * 100.0
+            ),
+            self.debug_overlay_format.clone()?,
+            D2D_RECT_F {
+                left: OVERLAY_TEXT_PADDING,
+                top: OVERLAY_TEXT_PADDING,
+                right: (self.screen_width - OVERLAY_TEXT_PADDING).max(OVERLAY_TEXT_PADDING),
+                bottom: (self.screen_height - OVERLAY_TEXT_PADDING).max(OVERLAY_TEXT_PADDING),
+            },
+            DEBUG_OVERLAY_COLOR,
+        ))
+    }
+
+    /// Motion blur to use at the current quality level, it is the first effect to go
+    fn motion_blur(&self) -> f32 {
+        if self.quality_level >= MAX_QUALITY_LEVEL {
+            self.config.motion_blur
+        } else {
+            0.0
+        }
+    }
+
+    /// Color multiplied into roads as they are drawn, None when `time_of_day_tint` is off
+    fn road_tint(&self) -> Option<D2D1_COLOR_F> {
+        self.config
+            .time_of_day_tint
+            .then(|| time_of_day_tint(local_hour()))
+    }
+
+    /// `operations` with `tint` applied, the recorded history keeps the untinted colors
+    fn tinted(mut operations: Vec<DrawOperation>, tint: D2D1_COLOR_F) -> Vec<DrawOperation> {
+        for operation in &mut operations {
+            operation.tint(tint);
+        }
+        operations
+    }
+
+    /// Index of the palette the current city picks its colors from
+    fn palette_index(&self) -> usize {
+        self.palette_index.unwrap_or(0)
+    }
+
+    /// Count a finished city and start the next one, unless `max_cycles` is reached
+    fn complete_cycle(&mut self) {
+        self.completed_cycles += 1;
+        if self
+            .config
+            .max_cycles
+            .is_some_and(|max_cycles| self.completed_cycles >= max_cycles)
+        {
+            info!("Completed {} cycles, finishing", self.completed_cycles);
+            self.finished = true;
+            return;
+        }
+        self.phase_changes.push(AnimationPhase::Restarting);
+        self.initialize(self.config.start_branches as usize);
+    }
+
+    /// Whether the frames of the hold phase are all the same (no pulse, no debug overlays)
+    fn is_hold_static(&self) -> bool {
+        let pulsing = self.quality_level > 0
+            && self.config.pulse_depth > 0.0
+            && self.config.pulse_speed > 0.0;
+        !pulsing && !self.debug_grid && !self.config.show_debug_overlay
+    }
+
+    /// Brightness multiplier of the held city, starting at full brightness
+    fn pulse_brightness(&self, elapsed: f32) -> f32 {
+        if self.quality_level == 0 {
+            return 1.0;
+        }
+        let phase = elapsed * self.config.pulse_speed * std::f32::consts::TAU;
+        1.0 - self.config.pulse_depth * (1.0 - phase.cos()) / 2.0
+    }
+
+    /// Process reverse animation step
+    /// Non-main branches erase first, then main branches
+    fn reverse_step(
+        &mut self,
+        renderer: &mut Renderer,
+        total_actions: usize,
+        delta_time: f32,
+    ) -> Result<bool> {
+        if self.painter_state.draw_history.is_empty() {
+            return Ok(true); // Done reversing
+        }
+
+        let budget = self.reverse_budget(total_actions, delta_time);
+        match self.config.reverse_order {
+            ReverseOrder::Simultaneous => self.reverse_step_simultaneous(renderer, budget),
+            ReverseOrder::ByBranch => self.reverse_step_by_branch(renderer, budget),
+        }
+    }
+
+    /// Number of history entries to erase this frame
+    fn reverse_budget(&self, total_actions: usize, delta_time: f32) -> usize {
+        let cap = self.config.reverse_actions_per_frame.max(1);
+        if self.config.reverse_fraction_per_second <= 0.0 {
+            return cap;
+        }
+        let target =
+            (total_actions as f32 * self.config.reverse_fraction_per_second * delta_time).ceil();
+        (target as usize).clamp(1, cap)
+    }
+
+    /// Number of growth steps to take this frame
+    fn growth_steps(&mut self, delta_time: f32) -> u32 {
+        let rate = self.config.growth_steps_per_second;
+        if rate <= 0.0 {
+            if self.quality_level > 0 {
+                return 1;
+            }
+            // Frames are too slow, only grow every other one
+            self.growth_time_accumulator += LOW_QUALITY_STEPS_PER_FRAME;
+            let steps = self.growth_time_accumulator.floor();
+            self.growth_time_accumulator -= steps;
+            return steps as u32;
+        }
+        let max_steps =
+            MAX_GROWTH_STEPS_PER_FRAME[self.quality_level.min(MAX_QUALITY_LEVEL) as usize];
+        self.growth_time_accumulator += delta_time;
+        let steps = (self.growth_time_accumulator * rate).floor() as u32;
+        self.growth_time_accumulator -= steps as f32 / rate;
+        if steps > max_steps {
+            self.growth_time_accumulator = 0.0;
+        }
+        steps.min(max_steps)
+    }
+
+    /// Total number of recorded history entries over all branches
+    fn history_len(&self) -> usize {
+        self.painter_state.draw_history.values().map(Vec::len).sum()
+    }
+
+    /// Whether `active` growing branches reach `max_active_branches`
+    fn at_branch_cap(&mut self, active: usize) -> bool {
+        let cap = self.config.max_active_branches;
+        if cap == 0 || active < cap {
+            return false;
+        }
+        if !self.branch_cap_reached {
+            info!(
+                "Reached max_active_branches ({}), branching off paused",
+                cap
+            );
+            self.branch_cap_reached = true;
+        }
+        true
+    }
+
+    /// Whether the history holds more than `max_total_actions` operations
+    fn over_action_cap(&mut self) -> bool {
+        let cap = self.config.max_total_actions;
+        if cap == 0 || self.history_len() <= cap {
+            return false;
+        }
+        if !self.action_cap_reached {
+            info!(
+                "Reached max_total_actions ({}) with {} active branches",
+                cap,
+                self.branch_list.len()
+            );
+            self.action_cap_reached = true;
+        }
+        true
+    }
+
+    /// Erase branches one at a time, last spawned first
+    /// Leftover budget of a frame carries over to the next branch, so the total duration
+    /// stays the same as with simultaneous reverse
+    fn reverse_step_by_branch(&mut self, renderer: &mut Renderer, budget: usize) -> Result<bool> {
+        let mut budget = budget;
+        let mut entries_to_erase = Vec::new();
+
+        while budget > 0 {
+            let Some(&branch_id) = self.painter_state.spawn_order.last() else {
+                break;
+            };
+
+            if let Some(history) = self.painter_state.draw_history.get_mut(&branch_id) {
+                let erased = self.config.reverse_direction.drain(history, budget);
+                budget -= erased.len();
+                entries_to_erase.extend(erased);
+                if !history.is_empty() {
+                    continue;
+                }
+            }
+
+            // Branch fully erased, move on to the previously spawned one
+            self.painter_state.spawn_order.pop();
+            self.painter_state.draw_history.remove(&branch_id);
+            self.painter_state.main_branches.remove(&branch_id);
+        }
+
+        self.batch_erase(renderer, &entries_to_erase)?;
+
+        Ok(self.painter_state.draw_history.is_empty())
+    }
+
+    /// Keep `start_branches` main branches growing by spawning new ones on free edge cells
+    fn respawn_from_edges(&mut self) {
+        let active_main_branches = self
+            .branch_list
+            .iter()
+            .filter(|branch| self.painter_state.main_branches.contains(&branch.id))
+            .count();
+
+        for _ in active_main_branches..self.config.start_branches as usize {
+            let Some(pos) = self.grid.random_free_edge_pos(&mut self.rng) else {
+                break;
+            };
+            let branch = Branch::new(pos, &self.config, self.palette_index(), &mut self.rng);
+            self.grid.occupy(pos, branch.district);
+            self.painter_state.main_branches.insert(branch.id);
+            self.painter_state.cells.insert(branch.id, vec![pos]);
+            debug!("Branch respawned at edge ({}, {})", pos.x, pos.y);
+            self.branch_list.push(branch);
+        }
+    }
+
+    /// Gradually erase the oldest finished branch while the grid is fuller than allowed
+    ///
+    /// Uses the same erasure as the reverse animation; once the branch is fully erased its
+    /// cells are freed for new roads.
+    fn retire_step(&mut self, renderer: &mut Renderer) -> Result<()> {
+        if self.painter_state.retiring.is_none() {
+            if self.occupancy() <= self.config.continuous_max_fill && !self.over_action_cap() {
+                return Ok(());
+            }
+            let active: HashSet<u32> = self.branch_list.iter().map(|branch| branch.id).collect();
+            self.painter_state.retiring = self
+                .painter_state
+                .spawn_order
+                .iter()
+                .copied()
+                .find(|branch_id| !active.contains(branch_id));
+        }
+        let Some(branch_id) = self.painter_state.retiring else {
+            return Ok(());
+        };
+
+        let mut entries_to_erase = Vec::new();
+        if let Some(history) = self.painter_state.draw_history.get_mut(&branch_id) {
+            let count = self
+                .config
+                .reverse_actions_per_frame
+                .max(1)
+                .min(history.len());
+            entries_to_erase.extend(history.drain(history.len() - count..).rev());
+        }
+        self.batch_erase(renderer, &entries_to_erase)?;
+
+        if self
+            .painter_state
+            .draw_history
+            .get(&branch_id)
+            .is_none_or(|history| history.is_empty())
+        {
+            for pos in self
+                .painter_state
+                .cells
+                .remove(&branch_id)
+                .unwrap_or_default()
+            {
+                self.grid.set(pos.x as u32, pos.y as u32, false);
+            }
+            self.painter_state.roads.remove(&branch_id);
+            // Growing branches may backtrack into the freed cells
+            for branch in &mut self.branch_list {
+                branch.reopen_fields();
+            }
+            self.painter_state.draw_history.remove(&branch_id);
+            self.painter_state.main_branches.remove(&branch_id);
+            self.painter_state.parents.remove(&branch_id);
+            self.painter_state
+                .spawn_order
+                .retain(|&spawned_id| spawned_id != branch_id);
+            self.painter_state.retiring = None;
+            debug!("Retired branch {}", branch_id);
+        }
+        Ok(())
+    }
+
+    /// Branches that still have a child with history left, so they must not be erased yet
+    fn branches_with_visible_children(&self) -> HashSet<u32> {
+        self.painter_state
+            .parents
+            .iter()
+            .filter(|(child_id, _)| self.painter_state.draw_history.contains_key(child_id))
+            .map(|(_, parent_id)| *parent_id)
+            .collect()
+    }
+
+    /// Erase all branches at once, non-main branches first, then main branches
+    fn reverse_step_simultaneous(
+        &mut self,
+        renderer: &mut Renderer,
+        budget: usize,
+    ) -> Result<bool> {
+        let blocked = if self.config.reverse_respects_tree {
+            self.branches_with_visible_children()
+        } else {
+            HashSet::new()
+        };
+        let (main_branch_ids, non_main_branch_ids): (Vec<u32>, Vec<u32>) = self
+            .painter_state
+            .draw_history
+            .keys()
+            .copied()
+            .filter(|branch_id| !blocked.contains(branch_id))
+            .partition(|branch_id| self.painter_state.main_branches.contains(branch_id));
+
+        // Decide which branches to process (non-main first, then main)
+        let branches_to_process = if !non_main_branch_ids.is_empty() {
             &non_main_branch_ids
         } else {
             &main_branch_ids
         };
 
         // Calculate how many entries to erase per branch
-        let entries_per_branch = (self.config.reverse_actions_per_frame.max(1) as f32
-            / branches_to_process.len().max(1) as f32)
-            .ceil() as usize;
+        let entries_per_branch =
+            (budget as f32 / branches_to_process.len().max(1) as f32).ceil() as usize;
 
         // Collect entries to erase
         let mut all_entries_to_erase = Vec::new();
@@ -919,15 +3615,16 @@ impl CityGrowScene {
                     continue;
                 }
 
-                let count = entries_per_branch.min(history.len());
-                let to_erase: Vec<DrawOperation> = history.drain(history.len() - count..).collect();
-                all_entries_to_erase.extend(to_erase);
+                all_entries_to_erase.extend(
+                    self.config
+                        .reverse_direction
+                        .drain(history, entries_per_branch),
+                );
             }
         }
 
         // Batch erase all entries efficiently (consolidates lines into polylines)
-        let entries_to_erase: Vec<DrawOperation> = all_entries_to_erase.into_iter().rev().collect();
-        self.batch_erase(renderer, &entries_to_erase)?;
+        self.batch_erase(renderer, &all_entries_to_erase)?;
 
         // Remove empty branches
         for branch_id in branches_to_remove {
@@ -941,77 +3638,315 @@ impl CityGrowScene {
 
 impl Scene for CityGrowScene {
     fn is_animating(&self) -> bool {
-        true
+        !self.finished
+            && !self.idle_forever
+            && self.idle_until.is_none_or(|until| Instant::now() >= until)
+    }
+
+    fn wake_after(&self) -> Option<Duration> {
+        self.idle_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Lower levels take fewer growth steps per frame. Level 1 also drops motion blur, level 0
+    /// the pulse while holding and, without `growth_steps_per_second`, grows only every other
+    /// frame.
+    fn set_quality_level(&mut self, level: u8) {
+        self.quality_level = level;
+    }
+
+    fn on_dpi_changed(&mut self, dpi: u32) {
+        self.dpi = dpi;
+        if !self.config.scale_with_dpi {
+            return;
+        }
+        self.config.scale = self.base_scale * dpi as f32 / 96.0;
+        debug!(
+            dpi,
+            scale = self.config.scale,
+            "Scaling city for display DPI"
+        );
+        self.on_resize(self.screen_width as u32, self.screen_height as u32);
+    }
+
+    fn take_phase_changes(&mut self) -> Vec<AnimationPhase> {
+        std::mem::take(&mut self.phase_changes)
+    }
+
+    fn save_state(&self, path: &Path) -> Result<()> {
+        CityGrowScene::save_state(self, path)
+    }
+
+    fn restore_state(&mut self, path: &Path) -> Result<()> {
+        let mut config = self.config.clone();
+        config.scale = self.base_scale;
+        let mut restored =
+            CityGrowScene::load_state(path, config)?.with_debug_grid(self.debug_grid);
+        restored.quality_level = self.quality_level;
+        *self = restored;
+        Ok(())
+    }
+
+    fn apply_config(&mut self, config: &CityGrowConfig) {
+        let mut current = self.config.clone();
+        current.scale = self.base_scale;
+        let changed = changed_fields(&current, &config.scene);
+        if changed.is_empty() {
+            return;
+        }
+        info!("Config reloaded, changed: {}", changed.join(", "));
+        if let Err(e) = config.scene.validate() {
+            warn!("Ignoring invalid scene config: {:?}", e);
+            return;
+        }
+
+        let is_changed =
+            |fields: &[&str]| changed.iter().any(|name| fields.contains(&name.as_str()));
+        let restart = is_changed(RESTART_FIELDS);
+        if is_changed(&[
+            "overlay_text",
+            "overlay_font",
+            "overlay_size",
+            "overlay_anchor",
+        ]) {
+            self.overlay_format = None;
+        }
+        if is_changed(&["overlay_font"]) {
+            self.district_label_format = None;
+        }
+        if is_changed(&["show_grid"]) {
+            self.grid_lines_changed = true;
+        }
+        if is_changed(&["background_image"]) {
+            // Erased roads reveal the background, so everything is drawn again on the new one
+            self.background_changed = true;
+            self.needs_initial_clear = true;
+            self.needs_replay = true;
+        }
+
+        self.config = config.scene.clone();
+        self.base_scale = self.config.scale;
+        if self.config.scale_with_dpi {
+            self.config.scale = self.base_scale * self.dpi as f32 / 96.0;
+        }
+        if let Some(seed) = self.config.seed
+            && is_changed(&["seed"])
+        {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+        if restart {
+            info!("Starting a new city with the reloaded config");
+            self.needs_replay = false;
+            self.on_resize(self.screen_width as u32, self.screen_height as u32);
+        }
+    }
+
+    fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    fn status(&self) -> String {
+        format!(
+            "phase={:?} active_branches={} main_branches={} branches_with_history={}",
+            self.phase,
+            self.branch_list.len(),
+            self.painter_state.main_branches.len(),
+            self.painter_state.draw_history.len()
+        )
     }
 
     fn prepare_render(&mut self, renderer: &mut Renderer) -> Result<()> {
+        // Before the intermediate bitmap exists, it is cleared to the matching background color
+        renderer.set_global_alpha(self.config.global_alpha);
+        renderer.set_antialias(self.config.antialiasing);
         renderer.incremental_no_copy()?;
+
+        if self.background_changed {
+            renderer.unload_background();
+            self.background_changed = false;
+        }
+        // The grid is static, recorded once into a command list the renderer replays every frame
+        if self.grid_lines_changed {
+            let grid_lines = if self.config.show_grid {
+                Some(renderer.create_command_list(&self.grid_line_operations())?)
+            } else {
+                None
+            };
+            renderer.set_static_overlay(grid_lines);
+            self.grid_lines_changed = false;
+        }
+        // A recreated renderer comes without the background, load it again
+        if let Some(path) = &self.config.background_image
+            && !renderer.has_background()
+            && let Err(e) = renderer.load_background(path)
+        {
+            warn!("Failed to load background image, using black: {:?}", e);
+            self.config.background_image = None;
+        }
+
+        if self.config.overlay_text.is_some() && self.overlay_format.is_none() {
+            let format =
+                renderer.create_text_format(&self.config.overlay_font, self.config.overlay_size)?;
+            self.config.overlay_anchor.apply(&format);
+            self.overlay_format = Some(format);
+        }
+        if self.config.district_label_min_cells > 0 && self.district_label_format.is_none() {
+            self.district_label_format = Some(
+                renderer.create_text_format(&self.config.overlay_font, DISTRICT_LABEL_FONT_SIZE)?,
+            );
+        }
+        if self.config.show_debug_overlay && self.debug_overlay_format.is_none() {
+            let format = renderer.create_text_format("Consolas", DEBUG_OVERLAY_FONT_SIZE)?;
+            TextAnchor::TopLeft.apply(&format);
+            self.debug_overlay_format = Some(format);
+        }
         Ok(())
     }
 
-    fn render(&mut self, renderer: &mut Renderer, _delta_time: f32) -> Result<()> {
-        // Clear background to black only once at start
+    fn render(&mut self, renderer: &mut Renderer, delta_time: f32) -> Result<()> {
+        // Nothing advances while paused (hold time, reverse budget), the last frame stays up
+        if self.paused {
+            return Ok(());
+        }
+
+        // Clear to the background only once at start
         if self.needs_initial_clear {
-            renderer.clear(D2D1_COLOR_F::black());
+            renderer.draw_background();
             self.needs_initial_clear = false;
         }
+        if self.needs_replay {
+            self.replay_history(renderer)?;
+            if self.config.rounded_joins && matches!(self.phase, Phase::Holding { .. }) {
+                self.smooth_finished_roads(renderer)?;
+            }
+            self.needs_replay = false;
+        }
 
-        // Handle reverse animation
-        if self.reverse_running {
-            let done = self.reverse_step(renderer)?;
-            if done {
-                // Restart the animation
-                debug!("Reverse animation complete, restarting");
-                self.initialize(self.config.start_branches as usize);
+        if self.config.district_label_min_cells > 0 {
+            renderer.queue_overlay(self.district_label_operations());
+        }
+        if self.debug_grid {
+            renderer.queue_overlay(self.debug_grid_operations());
+        }
+        if self.config.show_debug_overlay {
+            if delta_time > 0.0 {
+                let fps = 1.0 / delta_time;
+                self.debug_fps = if self.debug_fps == 0.0 {
+                    fps
+                } else {
+                    self.debug_fps + (fps - self.debug_fps) * DEBUG_FPS_SMOOTHING
+                };
             }
-            return Ok(());
+            renderer.queue_overlay(self.debug_overlay_operation());
         }
+        renderer.set_text_overlay(self.text_overlay());
 
-        // Generate events for this frame
-        let events = {
-            let mut events = self.process_branching();
-            events.extend(self.process_stepping());
-            events
-        };
+        match self.phase {
+            Phase::Growing => renderer.set_motion_blur(self.motion_blur())?,
+            Phase::Holding { elapsed } => {
+                // Keep the finished city on screen, nothing is drawn or erased
+                let mut elapsed = elapsed + delta_time;
+                if let Some(until) = self.idle_until {
+                    // Repainted while idle, the frame is unchanged
+                    if Instant::now() < until {
+                        return Ok(());
+                    }
+                    self.idle_until = None;
+                    elapsed = self.config.hold_before_reverse;
+                }
+                if elapsed >= self.config.hold_before_reverse
+                    && self.config.completion == CompletionMode::Restart
+                {
+                    debug!("Hold complete, clearing the city");
+                    renderer.set_present_opacity(1.0);
+                    renderer.set_motion_blur(0.0)?;
+                    self.complete_cycle();
+                    return Ok(());
+                }
+                self.phase = if elapsed >= self.config.hold_before_reverse {
+                    debug!("Hold complete, starting reverse animation");
+                    self.phase_changes.push(AnimationPhase::Reversing);
+                    renderer.set_present_opacity(1.0);
+                    renderer.set_motion_blur(0.0)?;
+                    Phase::Reversing {
+                        total_actions: self.history_len(),
+                    }
+                } else {
+                    renderer.set_present_opacity(self.pulse_brightness(elapsed));
+                    if self.is_hold_static() {
+                        // Settle a motion blurred frame onto the finished city before going idle
+                        renderer.set_motion_blur(0.0)?;
+                        let remaining = self.config.hold_before_reverse - elapsed;
+                        self.idle_until = Duration::try_from_secs_f32(remaining)
+                            .ok()
+                            .and_then(|remaining| Instant::now().checked_add(remaining));
+                        self.idle_forever = self.idle_until.is_none();
+                        debug!("Holding a static city, idle for {:.1}s", remaining);
+                    }
+                    Phase::Holding { elapsed }
+                };
+                return Ok(());
+            }
+            Phase::Reversing { total_actions } => {
+                let done = self.reverse_step(renderer, total_actions, delta_time)?;
+                if done {
+                    debug!("Reverse animation complete, restarting");
+                    self.complete_cycle();
+                }
+                return Ok(());
+            }
+        }
 
-        // Separate events into non-main and main branch events for proper layering
-        // Non-main branches are drawn first (appear below), main branches last (appear on top)
-        let mut non_main_operations = Vec::new();
-        let mut main_operations = Vec::new();
+        let tint = self.road_tint();
+        for _ in 0..self.growth_steps(delta_time) {
+            let (mut non_main_operations, mut main_operations) = self.grow_step();
+            if let Some(tint) = tint {
+                non_main_operations = Self::tinted(non_main_operations, tint);
+                main_operations = Self::tinted(main_operations, tint);
+            }
 
-        for event in events {
-            let (branch_id, operations) = self.event_to_draw_operations(&event);
+            if self.config.completion == CompletionMode::Continuous {
+                // Erase before drawing, so new roads crossing retired cells survive
+                self.retire_step(renderer)?;
+                renderer.set_normal_blend();
+                self.respawn_from_edges();
+            }
 
-            // Store in history for reverse animation
-            let branch_history = self
-                .painter_state
-                .draw_history
-                .entry(branch_id)
-                .or_default();
-            branch_history.extend(operations.iter().cloned());
-
-            // Separate by main/non-main for layering
-            if self.painter_state.main_branches.contains(&branch_id) {
-                main_operations.extend(operations);
-            } else {
-                non_main_operations.extend(operations);
+            // Batch draw non-main branches first (background)
+            if !non_main_operations.is_empty() {
+                renderer.draw_batch(&non_main_operations)?;
             }
-        }
 
-        // Batch draw non-main branches first (background)
-        if !non_main_operations.is_empty() {
-            renderer.draw_batch(&non_main_operations)?;
-        }
+            // Batch draw main branches last (foreground - on top)
+            if !main_operations.is_empty() {
+                renderer.draw_batch(&main_operations)?;
+            }
 
-        // Batch draw main branches last (foreground - on top)
-        if !main_operations.is_empty() {
-            renderer.draw_batch(&main_operations)?;
+            if self.branch_list.is_empty() {
+                break;
+            }
         }
 
         // Check if all branches are exhausted
-        if self.branch_list.is_empty() && !self.reverse_running {
-            debug!("All branches exhausted, starting reverse animation");
-            self.reverse_running = true;
+        if self.branch_list.is_empty() && self.config.completion != CompletionMode::Continuous {
+            debug!(
+                "All branches exhausted, holding for {}s",
+                self.config.hold_before_reverse
+            );
+            self.phase = Phase::Holding { elapsed: 0.0 };
+            self.phase_changes.push(AnimationPhase::Holding);
+
+            if self.config.rounded_joins {
+                self.smooth_finished_roads(renderer)?;
+            }
         }
 
         Ok(())
@@ -1020,11 +3955,153 @@ impl Scene for CityGrowScene {
     fn on_resize(&mut self, width: u32, height: u32) {
         self.screen_width = width as f32;
         self.screen_height = height as f32;
+        self.grid_lines_changed = true;
 
-        let cell_count_x = (self.screen_width / self.config.scale / 2.0).round() as u32;
-        let cell_count_y = (self.screen_height / self.config.scale / 2.0).round() as u32;
-        self.grid = Grid::new(cell_count_x, cell_count_y);
+        let (cell_count_x, cell_count_y) = Self::cell_counts(width, height, self.config.scale);
+        // A restored state survives the first resize if the window matches the saved grid
+        if self.needs_replay && self.grid_size() == (cell_count_x, cell_count_y) {
+            self.needs_initial_clear = true;
+            return;
+        }
+        self.grid = Grid::new(
+            cell_count_x,
+            cell_count_y,
+            self.config.wrap_edges,
+            self.config.territorial,
+            self.config.allow_diagonal,
+        );
 
         self.initialize(self.config.start_branches as usize);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_counts_follow_the_screen_orientation() {
+        let config = CityGrowSceneConfig {
+            scale: 2.0,
+            ..Default::default()
+        };
+        // One cell every 2 * scale pixels along each axis
+        for ((width, height), cells) in [
+            ((1080, 1920), (270, 480)),
+            ((1920, 1080), (480, 270)),
+            ((1440, 1440), (360, 360)),
+        ] {
+            let scene = CityGrowScene::with_config_and_seed(width, height, config.clone(), 1);
+            assert_eq!(scene.grid_size(), cells, "{width}x{height}");
+        }
+
+        // Rotating the screen rebuilds the grid the other way around
+        let mut scene = CityGrowScene::with_config_and_seed(1920, 1080, config, 1);
+        scene.on_resize(1080, 1920);
+        assert_eq!(scene.grid_size(), (270, 480));
+    }
+
+    #[test]
+    fn block_margin_insets_the_fill_rect() {
+        let fill_rect = |block_margin: f32| {
+            let config = CityGrowSceneConfig {
+                scale: 10.0,
+                block_margin,
+                ..Default::default()
+            };
+            let scene = CityGrowScene::with_config_and_seed(400, 400, config, 1);
+            scene.compute_fill_rect(Pos::new(3, 4), Pos::new(4, 4), Pos::new(0, 1))
+        };
+
+        let full = fill_rect(0.0);
+        // Block in the gap right and below of cell (3, 4), one scale wide
+        assert_eq!(
+            (full.left, full.top, full.right, full.bottom),
+            (70.0, 90.0, 80.0, 100.0)
+        );
+
+        let inset = fill_rect(4.0);
+        assert_eq!(inset.left, full.left + 4.0);
+        assert_eq!(inset.top, full.top + 4.0);
+        assert_eq!(inset.right, full.right - 4.0);
+        assert_eq!(inset.bottom, full.bottom - 4.0);
+    }
+
+    /// Events of one simulation step, without drawing anything
+    fn step_events(scene: &mut CityGrowScene) -> Vec<Event> {
+        let mut events = scene.process_branching();
+        events.extend(scene.process_stepping());
+        events
+    }
+
+    /// Share of land mode moves that change the direction of the previous move
+    fn land_turn_ratio(land_directional_bias: f32) -> f32 {
+        let config = CityGrowSceneConfig {
+            start_branches: 4,
+            life_time: 500,
+            life_time_branch: 1,
+            prop_city_to_land: 1.0,
+            prop_land_to_city: 0.0,
+            prop_branch_off_city: 0.0,
+            prop_branch_off_land: 0.0,
+            land_directional_bias,
+            ..Default::default()
+        };
+        let (mut moves, mut turns) = (0, 0);
+        for seed in 1..=5 {
+            let mut scene = CityGrowScene::with_config_and_seed(400, 400, config.clone(), seed);
+            let mut directions: HashMap<u32, Pos> = HashMap::new();
+            for _ in 0..200 {
+                for event in step_events(&mut scene) {
+                    let Event::Move {
+                        branch_id,
+                        from,
+                        to,
+                        mode: BranchMode::Land,
+                        ..
+                    } = event
+                    else {
+                        continue;
+                    };
+                    let direction = Pos::new(to.x - from.x, to.y - from.y);
+                    if let Some(previous) = directions.insert(branch_id, direction) {
+                        moves += 1;
+                        turns += usize::from(previous != direction);
+                    }
+                }
+            }
+        }
+        assert!(moves > 0);
+        turns as f32 / moves as f32
+    }
+
+    #[test]
+    fn land_directional_bias_straightens_highways() {
+        let meandering = land_turn_ratio(1.0);
+        let straight = land_turn_ratio(8.0);
+        assert!(
+            straight < meandering / 2.0,
+            "turn ratio {straight} with bias 8 vs {meandering} with bias 1"
+        );
+    }
+
+    #[test]
+    fn same_seed_grows_the_same_city() {
+        let config = CityGrowSceneConfig::default();
+        let mut first = CityGrowScene::with_config_and_seed(640, 480, config.clone(), 42);
+        let mut second = CityGrowScene::with_config_and_seed(640, 480, config.clone(), 42);
+        for step in 0..300 {
+            let first_events = format!("{:?}", step_events(&mut first));
+            let second_events = format!("{:?}", step_events(&mut second));
+            assert_eq!(first_events, second_events, "step {step}");
+        }
+
+        // A different seed starts elsewhere
+        let mut first = CityGrowScene::with_config_and_seed(640, 480, config.clone(), 42);
+        let mut other = CityGrowScene::with_config_and_seed(640, 480, config, 43);
+        assert_ne!(
+            format!("{:?}", step_events(&mut first)),
+            format!("{:?}", step_events(&mut other))
+        );
+    }
+}
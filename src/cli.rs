@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+/// Command line options passed to the executable
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    /// Render a diagnostic test pattern instead of the city scene
+    pub self_test: bool,
+    /// Advance the animation by one frame per keypress instead of continuously
+    pub step: bool,
+    /// Draw the occupied grid cells and branch positions on top of the scene
+    pub debug_grid: bool,
+    /// Monitor this instance renders to, keeps single-instance guards and state files of
+    /// monitors apart (None = the monitor the host places the window on)
+    pub monitor: Option<String>,
+    /// Write a preview image of a finished city to this path and exit
+    pub thumbnail: Option<PathBuf>,
+    /// Write the drawing commands of a finished city as newline-delimited JSON and exit
+    pub export: Option<PathBuf>,
+    /// Write a finished city as an SVG image and exit
+    pub svg: Option<PathBuf>,
+    /// Show the animation in a normal resizable window of this size instead of fullscreen
+    pub windowed: Option<(u32, u32)>,
+}
+
+impl CliArgs {
+    /// Parse options from the process arguments, ignoring unknown ones
+    /// (Lively may pass its own arguments to the executable)
+    pub fn parse() -> Self {
+        let mut args = Self::default();
+        let mut raw_args = std::env::args().skip(1);
+        while let Some(arg) = raw_args.next() {
+            match arg.as_str() {
+                "--selftest" => args.self_test = true,
+                "--step" => args.step = true,
+                "--debug-grid" => args.debug_grid = true,
+                "--monitor" => args.monitor = raw_args.next(),
+                "--thumbnail" => args.thumbnail = raw_args.next().map(PathBuf::from),
+                "--export" => args.export = raw_args.next().map(PathBuf::from),
+                "--svg" => args.svg = raw_args.next().map(PathBuf::from),
+                "--windowed" => args.windowed = raw_args.next().as_deref().and_then(parse_size),
+                _ => {}
+            }
+        }
+        args
+    }
+}
+
+/// Parse a `WIDTHxHEIGHT` size such as `1280x720`, both at least 1
+fn parse_size(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once(['x', 'X'])?;
+    let (width, height) = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (width > 0 && height > 0).then_some((width, height))
+}
@@ -1,31 +1,67 @@
 use std::path::{Path, PathBuf};
 
 use crate::city_grow::CityGrowSceneConfig;
-use anyhow::Result;
-use config::Config;
+use crate::renderer::swap_chain::SwapChainConfig;
+use crate::scene::{MAX_QUALITY_LEVEL, SceneId};
+use anyhow::{Context, Result, bail};
+use config::{Config, FileFormat};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Default, Serialize, Deserialize)]
+/// Configuration file, missing keys fall back to their defaults
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CityGrowConfig {
     pub app: AppConfig,
     pub scene: CityGrowSceneConfig,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
+    /// Scene to show, the `scene` settings only apply to `CityGrow`
+    pub animation: SceneId,
     pub framerate: u32,
+    /// Frame rate while the computer runs on battery (0 = keep `framerate`). Without
+    /// `growth_steps_per_second` the city also grows slower then.
+    pub battery_framerate: u32,
     pub default_width: u32,
     pub default_height: u32,
     pub log_level: LogLevel,
+    pub swap_chain: SwapChainConfig,
+    /// Lowest quality level the app may drop to when frames take longer than the frame budget,
+    /// equal to `max_quality_level` turns adaptive quality off
+    pub min_quality_level: u8,
+    pub max_quality_level: u8,
+    /// Stretch one city across all monitors instead of filling the window the host provides
+    pub span_monitors: bool,
+    /// Wait for the display refresh on every frame. Off renders uncapped, for benchmarking.
+    pub vsync: bool,
+    /// Save the city on exit and continue it on the next start (if the screen size matches)
+    pub resume: bool,
+    /// Let clicks pass through the window, for running without Lively
+    pub click_through: bool,
+    /// Keep the window below all other windows, for running without Lively
+    pub bottom_most: bool,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            animation: SceneId::CityGrow,
             framerate: 60,
+            battery_framerate: 30,
             default_width: 1920,
             default_height: 1080,
             log_level: LogLevel::Info,
+            swap_chain: SwapChainConfig::default(),
+            min_quality_level: MAX_QUALITY_LEVEL,
+            max_quality_level: MAX_QUALITY_LEVEL,
+            span_monitors: false,
+            vsync: true,
+            resume: false,
+            click_through: false,
+            bottom_most: false,
         }
     }
 }
@@ -43,19 +79,76 @@ impl CityGrowConfig {
     }
 
     pub fn load_config(path: &Path) -> Result<Self> {
-        let config = Config::builder()
-            .add_source(config::File::from(Self::config_path_from_dir(path)))
-            .build()?;
+        Self::load_file(config::File::from(Self::config_path_from_dir(path)))
+    }
+
+    /// TOML configuration in the app directory, takes precedence over the YAML one if present
+    pub fn toml_path_from_dir(app_dir: &Path) -> PathBuf {
+        app_dir.join("city_grow.toml")
+    }
+
+    pub fn from_toml_path(path: &Path) -> Result<Self> {
+        Self::load_file(config::File::from(path).format(FileFormat::Toml))
+    }
+
+    /// File the configuration is read from: the TOML one if present, the YAML one otherwise
+    pub fn active_path(app_dir: &Path) -> PathBuf {
+        let toml_path = Self::toml_path_from_dir(app_dir);
+        if toml_path.exists() {
+            toml_path
+        } else {
+            Self::config_path_from_dir(app_dir)
+        }
+    }
+
+    /// Read a TOML or YAML configuration, the format follows the file extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        Self::load_file(config::File::from(path))
+    }
+
+    fn load_file(file: config::File<config::FileSourceFile, FileFormat>) -> Result<Self> {
+        let config = Config::builder().add_source(file).build()?;
         let city_grow_config: CityGrowConfig = config.try_deserialize()?;
+        city_grow_config.validate()?;
         Ok(city_grow_config)
     }
 
+    /// Check all values are in range, so mistakes in the file are reported instead of clamped
+    pub fn validate(&self) -> Result<()> {
+        if self.app.framerate == 0 {
+            bail!("framerate must be at least 1");
+        }
+        self.app
+            .swap_chain
+            .validate()
+            .context("Invalid swap chain configuration")?;
+        self.scene
+            .validate()
+            .context("Invalid scene configuration")?;
+        Ok(())
+    }
+
     fn config_path_from_dir(app_dir: &Path) -> PathBuf {
         app_dir.join("city_grow.yaml")
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Names of the top-level fields whose values differ between `old` and `new`
+///
+/// Compares the serialized values, so new config fields are covered without listing them here.
+pub fn changed_fields<T: Serialize>(old: &T, new: &T) -> Vec<String> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+    new.into_iter()
+        .filter(|(name, value)| old.get(name) != Some(value))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum LogLevel {
     Error,
     Warn,
@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::{debug, info};
+use windows::{
+    Win32::{
+        Foundation::{
+            CloseHandle, ERROR_ALREADY_EXISTS, GetLastError, HANDLE, HWND, WAIT_ABANDONED,
+            WAIT_OBJECT_0,
+        },
+        Graphics::Gdi::{
+            GetMonitorInfoW, MONITOR_DEFAULTTONULL, MONITORINFOEXW, MonitorFromWindow,
+        },
+        System::Threading::{CreateMutexW, ReleaseMutex, WaitForSingleObject},
+    },
+    core::PCWSTR,
+};
+
+/// How long to wait for a previous instance on the same monitor to exit
+const PREVIOUS_INSTANCE_TIMEOUT_MS: u32 = 3000;
+
+/// Device name (e.g. `\\.\DISPLAY2`) of the monitor `hwnd` mostly lies on, None if it lies
+/// on none. Hosts like Lively start one instance per monitor without telling it which one, so
+/// this keeps them apart once the window was placed.
+pub fn monitor_id(hwnd: HWND) -> Option<String> {
+    unsafe {
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL);
+        if monitor.is_invalid() {
+            return None;
+        }
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
+        if !GetMonitorInfoW(monitor, &mut info.monitorInfo).as_bool() {
+            return None;
+        }
+        let len = info
+            .szDevice
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(info.szDevice.len());
+        Some(String::from_utf16_lossy(&info.szDevice[..len])).filter(|name| !name.is_empty())
+    }
+}
+
+/// File in `dir` the scene state of the instance on `monitor` is saved to
+pub fn state_path(dir: &Path, monitor: &str) -> PathBuf {
+    let monitor: String = monitor
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect();
+    dir.join(format!("city_grow_state_{monitor}.json"))
+}
+
+/// Named mutex ensuring only one wallpaper instance renders to a monitor at a time
+///
+/// Lively may relaunch the wallpaper (e.g. on monitor wake) before the old process has exited.
+/// The new instance waits briefly for the old one to release the mutex instead of both
+/// creating composition targets at the same time. The mutex is released on drop.
+pub struct InstanceGuard {
+    mutex: HANDLE,
+}
+
+impl InstanceGuard {
+    /// Acquire the guard for the given monitor
+    ///
+    /// Returns Ok(None) if another instance still holds the guard after the timeout.
+    pub fn acquire(monitor: &str) -> Result<Option<Self>> {
+        // Backslashes are reserved for the kernel object namespace prefix
+        let name = format!("Local\\CityGrowLively_{}", monitor.replace('\\', "_"));
+        let name_wide: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+
+        unsafe {
+            let mutex = CreateMutexW(None, true, PCWSTR::from_raw(name_wide.as_ptr()))
+                .context("Failed to create single-instance mutex")?;
+
+            if GetLastError() != ERROR_ALREADY_EXISTS {
+                debug!("Acquired single-instance mutex {}", name);
+                return Ok(Some(Self { mutex }));
+            }
+
+            info!(
+                "Another instance is running on monitor {}, waiting for it to exit",
+                monitor
+            );
+            let wait_result = WaitForSingleObject(mutex, PREVIOUS_INSTANCE_TIMEOUT_MS);
+            // An abandoned mutex means the previous instance exited without releasing it
+            if wait_result == WAIT_OBJECT_0 || wait_result == WAIT_ABANDONED {
+                debug!("Acquired single-instance mutex {} after waiting", name);
+                Ok(Some(Self { mutex }))
+            } else {
+                let _ = CloseHandle(mutex);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ReleaseMutex(self.mutex);
+            let _ = CloseHandle(self.mutex);
+        }
+    }
+}
@@ -1,28 +1,39 @@
 #![windows_subsystem = "windows"]
 
+use std::io::BufRead;
 use std::path::Path;
 use std::{env::current_exe, path::PathBuf};
 
 use anyhow::{Context, Result};
 use app::App;
+use serde::Deserialize;
 use tracing::{debug, info, warn};
 use tracing_appender::non_blocking::WorkerGuard;
-use window::Window;
+use window::{WM_APP_PAUSE, WM_APP_RESUME, Window};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
 use windows::Win32::Media::timeBeginPeriod;
 use windows::Win32::Media::timeEndPeriod;
 use windows::Win32::UI::HiDpi::{
     DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, SetProcessDpiAwarenessContext,
 };
+use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_CLOSE};
 
+use crate::cli::CliArgs;
 use crate::config::CityGrowConfig;
+use crate::instance_guard::InstanceGuard;
+use crate::scene::{Scene, SceneId};
+use crate::self_test::SelfTestScene;
 use crate::{city_grow::CityGrowScene, window::WindowConfigBuilder};
 
 mod app;
 mod city_grow;
+mod cli;
 mod config;
 mod ext;
+mod instance_guard;
 mod renderer;
 mod scene;
+mod self_test;
 mod window;
 
 fn initialize_logging(level: tracing::Level, log_dir: &Path) -> WorkerGuard {
@@ -46,7 +57,97 @@ fn initialize_logging(level: tracing::Level, log_dir: &Path) -> WorkerGuard {
     guard
 }
 
+/// Size of the preview image written with `--thumbnail`
+const THUMBNAIL_SIZE: (u32, u32) = (640, 360);
+
+/// Create the scene selected by `id`, new scenes only need an arm here
+fn make_scene(
+    id: SceneId,
+    config: &CityGrowConfig,
+    args: &CliArgs,
+    state_path: Option<&Path>,
+) -> Box<dyn Scene> {
+    match id {
+        SceneId::SelfTest => {
+            info!("Running in self-test mode");
+            Box::new(SelfTestScene::new())
+        }
+        SceneId::CityGrow => {
+            let restored = if config.app.resume
+                && let Some(state_path) = state_path
+                && state_path.exists()
+            {
+                CityGrowScene::load_state(state_path, config.scene.clone())
+                    .inspect_err(|e| warn!("Starting a new city: {:?}", e))
+                    .ok()
+            } else {
+                None
+            };
+            let scene = restored
+                .unwrap_or_else(|| {
+                    CityGrowScene::with_config(
+                        config.app.default_width,
+                        config.app.default_height,
+                        config.scene.clone(),
+                    ) // Initial size, will be updated on first resize
+                })
+                .with_debug_grid(args.debug_grid);
+            Box::new(scene)
+        }
+    }
+}
+
+/// Command line sent by the host on stdin, e.g. `{"command":"pause"}`
+#[derive(Deserialize)]
+struct HostCommand {
+    command: String,
+}
+
+/// Read host commands from stdin on a background thread and forward them to the window as
+/// messages. Ends quietly when stdin is closed or missing (no host attached).
+fn spawn_stdin_commands(hwnd: HWND) {
+    // Window handles are valid on every thread, the raw value just isn't Send
+    let hwnd = hwnd.0 as isize;
+    let spawned = std::thread::Builder::new()
+        .name("stdin commands".to_string())
+        .spawn(move || {
+            let hwnd = HWND(hwnd as *mut _);
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let message = match serde_json::from_str::<HostCommand>(&line) {
+                    Ok(HostCommand { command }) => match command.as_str() {
+                        "pause" => WM_APP_PAUSE,
+                        "resume" => WM_APP_RESUME,
+                        "close" => WM_CLOSE,
+                        _ => {
+                            debug!("Ignoring host command {:?}", command);
+                            continue;
+                        }
+                    },
+                    Err(e) => {
+                        debug!("Ignoring stdin line {:?}: {}", line, e);
+                        continue;
+                    }
+                };
+                debug!("Host command: {}", line.trim());
+                unsafe {
+                    let _ = PostMessageW(Some(hwnd), message, WPARAM(0), LPARAM(0));
+                }
+            }
+            debug!("Stdin closed, no more host commands");
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start the stdin command reader: {:?}", e);
+    }
+}
+
 fn main() -> Result<()> {
+    let args = CliArgs::parse();
     let app_dir = current_exe()
         .ok()
         .and_then(|p| p.parent().map(|p| p.to_path_buf()))
@@ -60,15 +161,62 @@ fn main() -> Result<()> {
         }
     }
 
-    if !CityGrowConfig::exists(&app_dir) {
-        CityGrowConfig::write_default(&app_dir).context("Failed to write default config")?;
+    let toml_path = CityGrowConfig::toml_path_from_dir(&app_dir);
+    let config = if toml_path.exists() {
+        CityGrowConfig::from_toml_path(&toml_path).context("Failed to load TOML config")?
+    } else {
+        if !CityGrowConfig::exists(&app_dir) {
+            CityGrowConfig::write_default(&app_dir).context("Failed to write default config")?;
+        }
+        CityGrowConfig::load_config(&app_dir).context("Failed to load config")?
+    };
+
+    let _guard = initialize_logging(config.app.log_level.into(), &app_dir);
+
+    if let Some(path) = &args.thumbnail {
+        let (width, height) = THUMBNAIL_SIZE;
+        return CityGrowScene::save_thumbnail(&config.scene, width, height, path)
+            .context("Failed to render thumbnail");
+    }
+
+    if let Some(path) = &args.export {
+        return CityGrowScene::save_ndjson(
+            &config.scene,
+            config.app.default_width,
+            config.app.default_height,
+            path,
+        )
+        .context("Failed to export drawing commands");
     }
 
-    let config = CityGrowConfig::load_config(&app_dir).context("Failed to load config")?;
+    if let Some(path) = &args.svg {
+        return CityGrowScene::save_svg(
+            &config.scene,
+            config.app.default_width,
+            config.app.default_height,
+            path,
+        )
+        .context("Failed to export SVG");
+    }
 
-    let _guard = initialize_logging(config.app.log_level.into(), &app_dir);
     info!("Starting City Grow animation");
 
+    // Without `--monitor` (Lively passes none) the app takes the guard of the monitor the
+    // host places the window on
+    let instance_guard = match &args.monitor {
+        Some(monitor) => {
+            let Some(guard) = InstanceGuard::acquire(monitor)? else {
+                warn!(
+                    "Another instance is still running on monitor {}, exiting",
+                    monitor
+                );
+                return Ok(());
+            };
+            Some(guard)
+        }
+        None => None,
+    };
+
     // Enable high-precision timing (1ms resolution instead of 15-16ms)
     // This significantly improves frame timing accuracy for smooth animation
     unsafe {
@@ -79,24 +227,56 @@ fn main() -> Result<()> {
         }
     }
 
-    let scene = CityGrowScene::with_config(
-        config.app.default_width,
-        config.app.default_height,
-        config.scene,
-    ); // Initial size, will be updated on first resize
-    let app = App::new(scene);
-    let _window = Window::create(
-        WindowConfigBuilder::default()
-            .title("City Grow".to_string())
-            .fullscreen(true) // Borderless fullscreen for Lively wallpaper
-            .target_framerate(config.app.framerate)
-            .build()?,
-        app,
-    )
-    .context("Failed to create window")?;
+    if let Some((width, height)) = args.windowed {
+        info!(width, height, "Running in a window");
+    }
+    let window_config = WindowConfigBuilder::default()
+        .title("City Grow".to_string())
+        .fullscreen(args.windowed.is_none()) // Borderless fullscreen for Lively wallpaper
+        .width(args.windowed.map(|(width, _)| width))
+        .height(args.windowed.map(|(_, height)| height))
+        .target_framerate(config.app.framerate)
+        .span_virtual_desktop(config.app.span_monitors)
+        .vsync(config.app.vsync)
+        .click_through(config.app.click_through)
+        .bottom_most(config.app.bottom_most)
+        .build()?;
+
+    // One state file per monitor, so instances on different monitors don't mix cities
+    let state_path = args
+        .monitor
+        .as_deref()
+        .map(|monitor| instance_guard::state_path(&app_dir, monitor));
+    let scene_id = if args.self_test {
+        SceneId::SelfTest
+    } else {
+        config.app.animation
+    };
+    let scene = make_scene(scene_id, &config, &args, state_path.as_deref());
+
+    let app = App::new(scene)
+        .with_adaptive_quality(
+            config.app.framerate,
+            config.app.min_quality_level,
+            config.app.max_quality_level,
+        )
+        .with_framerate(config.app.framerate, config.app.battery_framerate)
+        .with_step_mode(args.step)
+        .with_swap_chain_config(config.app.swap_chain.clone())
+        .with_vsync(config.app.vsync)
+        .with_span_monitors(config.app.span_monitors && args.windowed.is_none())
+        .with_capture_dir(app_dir.clone())
+        .with_state_path(state_path.filter(|_| config.app.resume))
+        .with_monitor_from_window(
+            args.monitor.is_none(),
+            config.app.resume.then(|| app_dir.clone()),
+        )
+        .with_config_watch(CityGrowConfig::active_path(&app_dir), config);
+    let window = Window::create(window_config, app).context("Failed to create window")?;
+    spawn_stdin_commands(window.hwnd());
 
     debug!("Entering message loop");
-    let result = Window::run_message_loop().context("Message loop failed");
+    let result = window.run_message_loop().context("Message loop failed");
     info!("Exiting");
 
     // Restore normal timer resolution
@@ -104,6 +284,7 @@ fn main() -> Result<()> {
         let _ = timeEndPeriod(1);
     }
 
+    drop(instance_guard);
     drop(_guard); // Keep guard alive by explicitly dropping it at the end
 
     result
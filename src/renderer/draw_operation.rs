@@ -1,4 +1,7 @@
-use windows::Win32::Graphics::Direct2D::Common::{D2D_RECT_F, D2D1_COLOR_F};
+use windows::Win32::Graphics::{
+    Direct2D::Common::{D2D_RECT_F, D2D1_COLOR_F},
+    DirectWrite::IDWriteTextFormat,
+};
 use windows_numerics::Vector2;
 
 /// Batch drawing operation
@@ -20,11 +23,61 @@ pub enum DrawOperation {
         rect: D2D_RECT_F,
         color: D2D1_COLOR_F,
     },
+    FilledRoundedRect {
+        rect: D2D_RECT_F,
+        radius_x: f32,
+        radius_y: f32,
+        color: D2D1_COLOR_F,
+    },
     Polyline {
         points: Vec<Vector2>,
         color: D2D1_COLOR_F,
         thickness: f32,
     },
+    /// Smoothed path through `points`: straight to the middle of the first segment, each
+    /// interior point rounded by a quadratic Bezier between the middles of its two segments,
+    /// straight from the middle of the last segment to the last point
+    BezierPath {
+        points: Vec<Vector2>,
+        color: D2D1_COLOR_F,
+        thickness: f32,
+    },
+    /// Rectangle filled with a linear gradient running from `start` to `end`
+    /// (in target coordinates, so neighboring rectangles continue the same gradient).
+    /// Stops are (position in 0..1, color) pairs.
+    GradientRect {
+        rect: D2D_RECT_F,
+        stops: Vec<(f32, D2D1_COLOR_F)>,
+        start: Vector2,
+        end: Vector2,
+    },
+    /// Ellipse around `center`, `thickness` is the outline width and unused when `filled`
+    Ellipse {
+        center: Vector2,
+        radius_x: f32,
+        radius_y: f32,
+        color: D2D1_COLOR_F,
+        filled: bool,
+        thickness: f32,
+    },
+    /// Circular arc around `center`. Angles are in degrees, measured clockwise on screen
+    /// from the positive X axis; a negative sweep runs counter-clockwise. Sweeps are
+    /// clamped to a full circle.
+    Arc {
+        center: Vector2,
+        radius: f32,
+        start_angle: f32,
+        sweep_angle: f32,
+        color: D2D1_COLOR_F,
+        thickness: f32,
+    },
+    /// Text laid out inside `rect`, aligned as configured on `format`
+    Text {
+        text: String,
+        format: IDWriteTextFormat,
+        rect: D2D_RECT_F,
+        color: D2D1_COLOR_F,
+    },
 }
 
 #[allow(dead_code)]
@@ -53,6 +106,21 @@ impl DrawOperation {
         Self::FilledRect { rect, color }
     }
 
+    /// Create a filled rectangle with rounded corners drawing operation
+    pub fn filled_rounded_rect(
+        rect: D2D_RECT_F,
+        radius_x: f32,
+        radius_y: f32,
+        color: D2D1_COLOR_F,
+    ) -> Self {
+        Self::FilledRoundedRect {
+            rect,
+            radius_x,
+            radius_y,
+            color,
+        }
+    }
+
     /// Create a polyline drawing operation
     pub fn polyline(points: Vec<Vector2>, color: D2D1_COLOR_F, thickness: f32) -> Self {
         Self::Polyline {
@@ -61,4 +129,197 @@ impl DrawOperation {
             thickness,
         }
     }
+
+    /// Create a smoothed path drawing operation
+    pub fn bezier_path(points: Vec<Vector2>, color: D2D1_COLOR_F, thickness: f32) -> Self {
+        Self::BezierPath {
+            points,
+            color,
+            thickness,
+        }
+    }
+
+    /// Create a gradient filled rectangle drawing operation
+    pub fn gradient_rect(
+        rect: D2D_RECT_F,
+        stops: Vec<(f32, D2D1_COLOR_F)>,
+        start: Vector2,
+        end: Vector2,
+    ) -> Self {
+        Self::GradientRect {
+            rect,
+            stops,
+            start,
+            end,
+        }
+    }
+
+    /// Create a filled ellipse drawing operation
+    pub fn filled_ellipse(
+        center: Vector2,
+        radius_x: f32,
+        radius_y: f32,
+        color: D2D1_COLOR_F,
+    ) -> Self {
+        Self::Ellipse {
+            center,
+            radius_x,
+            radius_y,
+            color,
+            filled: true,
+            thickness: 0.0,
+        }
+    }
+
+    /// Create an ellipse outline drawing operation
+    pub fn ellipse(
+        center: Vector2,
+        radius_x: f32,
+        radius_y: f32,
+        color: D2D1_COLOR_F,
+        thickness: f32,
+    ) -> Self {
+        Self::Ellipse {
+            center,
+            radius_x,
+            radius_y,
+            color,
+            filled: false,
+            thickness,
+        }
+    }
+
+    /// Rectangle containing every pixel the operation may touch, strokes included
+    pub fn bounds(&self) -> D2D_RECT_F {
+        match self {
+            Self::Line {
+                start,
+                end,
+                thickness,
+                ..
+            } => inflate(points_bounds(&[*start, *end]), *thickness),
+            Self::Rect {
+                rect, thickness, ..
+            } => inflate(*rect, *thickness),
+            Self::FilledRect { rect, .. }
+            | Self::FilledRoundedRect { rect, .. }
+            | Self::GradientRect { rect, .. }
+            | Self::Text { rect, .. } => *rect,
+            // Bezier control points are the path's points, the curve stays inside their hull
+            Self::Polyline {
+                points, thickness, ..
+            }
+            | Self::BezierPath {
+                points, thickness, ..
+            } => inflate(points_bounds(points), *thickness),
+            Self::Ellipse {
+                center,
+                radius_x,
+                radius_y,
+                filled,
+                thickness,
+                ..
+            } => {
+                let stroke = if *filled { 0.0 } else { *thickness };
+                D2D_RECT_F {
+                    left: center.X - radius_x - stroke,
+                    top: center.Y - radius_y - stroke,
+                    right: center.X + radius_x + stroke,
+                    bottom: center.Y + radius_y + stroke,
+                }
+            }
+            // Bounds of the whole circle, arcs are too rare to be worth a tighter fit
+            Self::Arc {
+                center,
+                radius,
+                thickness,
+                ..
+            } => inflate(points_bounds(&[*center]), radius + thickness),
+        }
+    }
+
+    /// Create an arc drawing operation, see `DrawOperation::Arc` for the angle convention
+    pub fn arc(
+        center: Vector2,
+        radius: f32,
+        start_angle: f32,
+        sweep_angle: f32,
+        color: D2D1_COLOR_F,
+        thickness: f32,
+    ) -> Self {
+        Self::Arc {
+            center,
+            radius,
+            start_angle,
+            sweep_angle,
+            color,
+            thickness,
+        }
+    }
+
+    /// Multiply every color of the operation by `tint`, channel by channel (alpha is kept)
+    pub fn tint(&mut self, tint: D2D1_COLOR_F) {
+        let apply = |color: &mut D2D1_COLOR_F| {
+            color.r *= tint.r;
+            color.g *= tint.g;
+            color.b *= tint.b;
+        };
+        match self {
+            Self::Line { color, .. }
+            | Self::Rect { color, .. }
+            | Self::FilledRect { color, .. }
+            | Self::FilledRoundedRect { color, .. }
+            | Self::Polyline { color, .. }
+            | Self::BezierPath { color, .. }
+            | Self::Ellipse { color, .. }
+            | Self::Arc { color, .. }
+            | Self::Text { color, .. } => apply(color),
+            Self::GradientRect { stops, .. } => {
+                stops.iter_mut().for_each(|(_, color)| apply(color));
+            }
+        }
+    }
+
+    /// Create a text drawing operation
+    pub fn text(
+        text: String,
+        format: IDWriteTextFormat,
+        rect: D2D_RECT_F,
+        color: D2D1_COLOR_F,
+    ) -> Self {
+        Self::Text {
+            text,
+            format,
+            rect,
+            color,
+        }
+    }
+}
+
+/// Smallest rectangle containing all `points`
+fn points_bounds(points: &[Vector2]) -> D2D_RECT_F {
+    points.iter().fold(
+        D2D_RECT_F {
+            left: f32::MAX,
+            top: f32::MAX,
+            right: f32::MIN,
+            bottom: f32::MIN,
+        },
+        |bounds, point| D2D_RECT_F {
+            left: bounds.left.min(point.X),
+            top: bounds.top.min(point.Y),
+            right: bounds.right.max(point.X),
+            bottom: bounds.bottom.max(point.Y),
+        },
+    )
+}
+
+/// Grow `rect` by `amount` on every side (a full stroke width covers caps and joins)
+fn inflate(rect: D2D_RECT_F, amount: f32) -> D2D_RECT_F {
+    D2D_RECT_F {
+        left: rect.left - amount,
+        top: rect.top - amount,
+        right: rect.right + amount,
+        bottom: rect.bottom + amount,
+    }
 }
@@ -0,0 +1,32 @@
+use std::fmt;
+use windows::core::HRESULT;
+
+/// Renderer failures the caller is expected to handle, carried inside `anyhow::Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RendererError {
+    /// The GPU device was removed or reset (driver update/crash, display mode change);
+    /// every device resource is gone and the renderer has to be recreated
+    DeviceLost {
+        /// HRESULT that reported the loss
+        code: HRESULT,
+        /// Result of `GetDeviceRemovedReason`
+        reason: HRESULT,
+    },
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DeviceLost { code, reason } => write!(
+                f,
+                "GPU device lost ({:#010X}: {}), removed reason {:#010X}: {}",
+                code.0,
+                code.message(),
+                reason.0,
+                reason.message()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
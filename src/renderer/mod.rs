@@ -1,28 +1,43 @@
 use anyhow::{Context, Result};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::mem::ManuallyDrop;
-use tracing::{debug, info};
+use std::path::Path;
+use tracing::{debug, info, warn};
 use windows::{
     Win32::{
-        Foundation::HWND,
+        Foundation::{
+            CloseHandle, D2DERR_RECREATE_TARGET, GENERIC_READ, HANDLE, HWND, RECT, S_OK,
+            WAIT_OBJECT_0,
+        },
         Graphics::{
             Direct2D::{
                 Common::{
-                    D2D_RECT_F, D2D_SIZE_U, D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_COLOR_F,
-                    D2D1_FIGURE_BEGIN_HOLLOW, D2D1_FIGURE_END_OPEN, D2D1_PIXEL_FORMAT,
+                    D2D_RECT_F, D2D_SIZE_F, D2D_SIZE_U, D2D1_ALPHA_MODE_PREMULTIPLIED,
+                    D2D1_COLOR_F, D2D1_FIGURE_BEGIN_HOLLOW, D2D1_FIGURE_END_OPEN,
+                    D2D1_GRADIENT_STOP, D2D1_PIXEL_FORMAT,
                 },
-                D2D1_ANTIALIAS_MODE_ALIASED, D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
-                D2D1_BITMAP_OPTIONS_TARGET, D2D1_BITMAP_PROPERTIES1, D2D1_CAP_STYLE_FLAT,
-                D2D1_DEVICE_CONTEXT_OPTIONS_NONE, D2D1_FACTORY_TYPE_SINGLE_THREADED,
-                D2D1_PRIMITIVE_BLEND_MIN, D2D1_PRIMITIVE_BLEND_SOURCE_OVER,
-                D2D1_STROKE_STYLE_PROPERTIES1, D2D1CreateFactory, ID2D1Bitmap1, ID2D1CommandList,
-                ID2D1Device, ID2D1DeviceContext, ID2D1Factory1, ID2D1SolidColorBrush,
-                ID2D1StrokeStyle,
+                D2D1_ANTIALIAS_MODE, D2D1_ANTIALIAS_MODE_ALIASED,
+                D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_ARC_SEGMENT, D2D1_ARC_SIZE_SMALL,
+                D2D1_BITMAP_OPTIONS_CANNOT_DRAW, D2D1_BITMAP_OPTIONS_CPU_READ,
+                D2D1_BITMAP_OPTIONS_NONE, D2D1_BITMAP_OPTIONS_TARGET, D2D1_BITMAP_PROPERTIES1,
+                D2D1_CAP_STYLE_FLAT, D2D1_DEVICE_CONTEXT_OPTIONS_NONE, D2D1_DRAW_TEXT_OPTIONS_NONE,
+                D2D1_ELLIPSE, D2D1_EXTEND_MODE_CLAMP, D2D1_FACTORY_TYPE_SINGLE_THREADED,
+                D2D1_GAMMA_2_2, D2D1_INTERPOLATION_MODE_LINEAR,
+                D2D1_INTERPOLATION_MODE_NEAREST_NEIGHBOR, D2D1_LINE_JOIN_ROUND,
+                D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES, D2D1_MAP_OPTIONS_READ,
+                D2D1_PRIMITIVE_BLEND_COPY, D2D1_PRIMITIVE_BLEND_MIN,
+                D2D1_PRIMITIVE_BLEND_SOURCE_OVER, D2D1_QUADRATIC_BEZIER_SEGMENT, D2D1_ROUNDED_RECT,
+                D2D1_STROKE_STYLE_PROPERTIES1, D2D1_SWEEP_DIRECTION_CLOCKWISE,
+                D2D1_SWEEP_DIRECTION_COUNTER_CLOCKWISE, D2D1CreateFactory, ID2D1Bitmap1,
+                ID2D1BitmapBrush1, ID2D1Brush, ID2D1CommandList, ID2D1Device, ID2D1DeviceContext,
+                ID2D1Factory, ID2D1Factory1, ID2D1LinearGradientBrush, ID2D1PathGeometry,
+                ID2D1RenderTarget, ID2D1SolidColorBrush, ID2D1StrokeStyle,
             },
             Direct3D::{
-                D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL, D3D_FEATURE_LEVEL_10_0,
-                D3D_FEATURE_LEVEL_10_1, D3D_FEATURE_LEVEL_11_0, D3D_FEATURE_LEVEL_11_1,
+                D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_UNKNOWN, D3D_DRIVER_TYPE_WARP, D3D_FEATURE_LEVEL,
+                D3D_FEATURE_LEVEL_10_0, D3D_FEATURE_LEVEL_10_1, D3D_FEATURE_LEVEL_11_0,
+                D3D_FEATURE_LEVEL_11_1,
             },
             Direct3D11::{
                 D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_CREATE_DEVICE_DEBUG, D3D11_SDK_VERSION,
@@ -34,28 +49,49 @@ use windows::{
             },
             DirectWrite::{
                 DWRITE_FACTORY_TYPE_SHARED, DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_NORMAL,
-                DWRITE_FONT_WEIGHT_NORMAL, DWRITE_PARAGRAPH_ALIGNMENT_CENTER,
-                DWRITE_TEXT_ALIGNMENT_CENTER, DWriteCreateFactory, IDWriteFactory,
-                IDWriteTextFormat,
+                DWRITE_FONT_WEIGHT_NORMAL, DWRITE_MEASURING_MODE_NATURAL,
+                DWRITE_PARAGRAPH_ALIGNMENT_CENTER, DWRITE_TEXT_ALIGNMENT_CENTER,
+                DWRITE_TEXT_METRICS, DWriteCreateFactory, IDWriteFactory, IDWriteTextFormat,
             },
             Dxgi::{
                 Common::{
-                    DXGI_ALPHA_MODE_PREMULTIPLIED, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_SAMPLE_DESC,
+                    DXGI_ALPHA_MODE_PREMULTIPLIED, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_UNKNOWN,
+                    DXGI_SAMPLE_DESC,
                 },
                 CreateDXGIFactory1, DXGI_ADAPTER_FLAG_SOFTWARE, DXGI_ERROR_DEVICE_REMOVED,
-                DXGI_ERROR_DEVICE_RESET, DXGI_PRESENT, DXGI_SCALING_STRETCH, DXGI_SWAP_CHAIN_DESC1,
-                DXGI_SWAP_EFFECT_FLIP_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT, IDXGIAdapter1,
-                IDXGIDevice, IDXGIFactory1, IDXGIFactory2, IDXGISurface, IDXGISwapChain1,
+                DXGI_ERROR_DEVICE_RESET, DXGI_FEATURE_PRESENT_ALLOW_TEARING, DXGI_PRESENT,
+                DXGI_PRESENT_ALLOW_TEARING, DXGI_PRESENT_PARAMETERS, DXGI_SCALING_STRETCH,
+                DXGI_SWAP_CHAIN_DESC1, DXGI_SWAP_CHAIN_FLAG, DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING,
+                DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT,
+                DXGI_USAGE_RENDER_TARGET_OUTPUT, IDXGIAdapter, IDXGIAdapter1, IDXGIDevice,
+                IDXGIFactory1, IDXGIFactory2, IDXGIFactory5, IDXGISurface, IDXGISwapChain1,
+                IDXGISwapChain2,
+            },
+            Imaging::{
+                GUID_WICPixelFormat32bppPBGRA, IWICPalette, WICBitmapDitherTypeNone,
+                WICBitmapPaletteTypeMedianCut, WICDecodeMetadataCacheOnDemand,
             },
         },
+        System::Threading::WaitForSingleObjectEx,
+        UI::HiDpi::GetDpiForWindow,
     },
-    core::{Interface, w},
+    core::{BOOL, HRESULT, HSTRING, Interface, w},
 };
-use windows_numerics::Vector2;
+use windows_numerics::{Matrix3x2, Vector2};
 
+use crate::ext::color_ext::D2DColorExt;
 use crate::renderer::draw_operation::DrawOperation;
+use crate::renderer::error::RendererError;
+use crate::renderer::offscreen::create_wic_factory;
+use crate::renderer::swap_chain::SwapChainConfig;
+use crate::renderer::text_overlay::TextOverlay;
 
 pub mod draw_operation;
+pub mod error;
+pub mod offscreen;
+pub mod svg;
+pub mod swap_chain;
+pub mod text_overlay;
 
 /// Low-level rendering backend using Direct2D + DirectComposition
 #[allow(dead_code)]
@@ -71,34 +107,182 @@ pub struct Renderer {
     d2d_bitmap: ID2D1Bitmap1, // Swap chain's back buffer
     // Intermediate render target for incremental rendering (avoids full scene redraws: 20% GPU → 1% GPU)
     intermediate_bitmap: Option<ID2D1Bitmap1>,
+    previous_frame_bitmap: Option<ID2D1Bitmap1>, // Last presented frame, kept for motion blur
+    intermediate_scale: u32, // Pixels per DIP of the intermediate bitmap, 2 when supersampling
+    // Supersampled intermediate bitmap filtered down to the target size, presented instead of it
+    resolved_bitmap: Option<ID2D1Bitmap1>,
 
     // Underlying D3D11 textures for efficient GPU-level copying (bypasses D2D pipeline)
     swap_chain_texture: ID3D11Texture2D,
     intermediate_texture: Option<ID3D11Texture2D>,
+    resolved_texture: Option<ID3D11Texture2D>,
 
     // DirectWrite
     dwrite_factory: IDWriteFactory,
 
-    // DirectComposition (for Windows 25H2)
-    swap_chain: IDXGISwapChain1,
-    composition_device: IDCompositionDevice,
-    _composition_target: IDCompositionTarget,
-    _composition_visual: IDCompositionVisual,
+    // DirectComposition (for Windows 25H2), None for headless renderers
+    presentation: Option<Presentation>,
+
+    // Operations drawn on top of the presented frame, outside the intermediate bitmap
+    overlay_operations: RefCell<Vec<DrawOperation>>,
+    text_overlay: Option<TextOverlay>,
+    static_overlay: Option<ID2D1CommandList>,
+    overlay_on_screen: Cell<bool>, // The last presented frame had an overlay
 
     // Performance optimization: brush cache (using RefCell for interior mutability)
     brush_cache: RefCell<HashMap<u32, ID2D1SolidColorBrush>>,
+    // Gradient brushes keyed on their stops (position bits, color key)
+    gradient_brush_cache: RefCell<HashMap<Vec<(u32, u32)>, ID2D1LinearGradientBrush>>,
 
     // Stroke style with flat caps (no rounded endpoints)
     flat_cap_stroke_style: ID2D1StrokeStyle,
+    // Stroke style with flat caps and round joins, used to smooth the corners of finished roads.
+    // Caps stay flat, round ones would reach into neighboring cells and erasing them would
+    // take bites out of the roads there.
+    round_join_stroke_style: ID2D1StrokeStyle,
+    round_joins: bool,
+    antialias: bool, // Off keeps lines pixel-perfect, erasure is always aliased
+
+    // Image drawn instead of the black clear, and a brush painting it back where roads are erased
+    background: Option<ID2D1Bitmap1>,
+    background_brush: Option<ID2D1BitmapBrush1>,
+    erasing: Cell<bool>, // MIN blend requested over a background image or a transparent clear
 
     // Rendering configuration
-    sync_interval: u32, // 0 = no vsync, 1 = vsync enabled
+    swap_chain_config: SwapChainConfig, // Kept to recreate the renderer after a device loss
+    sync_interval: u32,                 // 0 = no vsync, 1 = vsync enabled
+    present_opacity: f32, // Opacity of the intermediate bitmap when presented over black
+    dirty: Cell<DirtyRegion>, // What changed since the last Present
+    motion_blur: f32,     // Opacity of the previous frame blended over the new one (0 = off)
+    global_alpha: f32,    // Opacity of the whole frame over the desktop (1 = opaque black clear)
 
     // Metadata
     width: u32,
     height: u32,
+    dpi: u32,
+    adapter_name: String,
+}
+
+/// Part of the frame that changed since the last Present
+///
+/// Presenting only a dirty rectangle lets the compositor skip the rest of the screen, which
+/// matters for a wallpaper that is mostly idle.
+#[derive(Clone, Copy, PartialEq)]
+enum DirtyRegion {
+    Clean,
+    Rect(D2D_RECT_F),
+    Full,
+}
+
+/// Swap chain and the DirectComposition tree showing it in the window
+struct Presentation {
+    swap_chain: IDXGISwapChain1,
+    /// Created with DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING (vsync off and supported by the system),
+    /// so presents don't wait for the display
+    allow_tearing: bool,
+    /// Signaled when the swap chain can take another frame, with `frame_pacing`
+    frame_latency_object: Option<HANDLE>,
+    /// A wait on `frame_latency_object` succeeded and no Present has used up the frame yet.
+    /// Frames that don't present keep it, waiting again would block until the timeout.
+    frame_slot: Cell<bool>,
+    composition_device: IDCompositionDevice,
+    _composition_target: IDCompositionTarget,
+    _composition_visual: IDCompositionVisual,
+}
+
+impl Drop for Presentation {
+    fn drop(&mut self) {
+        if let Some(handle) = self.frame_latency_object {
+            unsafe {
+                let _ = CloseHandle(handle);
+            }
+        }
+    }
 }
 
+/// Path geometry of a `DrawOperation::BezierPath` through `points` (at least two)
+pub(crate) fn bezier_path_geometry(
+    factory: &ID2D1Factory,
+    points: &[Vector2],
+) -> Result<ID2D1PathGeometry> {
+    let midpoint = |a: Vector2, b: Vector2| Vector2 {
+        X: (a.X + b.X) / 2.0,
+        Y: (a.Y + b.Y) / 2.0,
+    };
+    unsafe {
+        let path = factory.CreatePathGeometry()?;
+        let sink = path.Open()?;
+        sink.BeginFigure(points[0], D2D1_FIGURE_BEGIN_HOLLOW);
+        sink.AddLine(midpoint(points[0], points[1]));
+        for window in points.windows(3) {
+            sink.AddQuadraticBezier(&D2D1_QUADRATIC_BEZIER_SEGMENT {
+                point1: window[1],
+                point2: midpoint(window[1], window[2]),
+            });
+        }
+        sink.AddLine(points[points.len() - 1]);
+        sink.EndFigure(D2D1_FIGURE_END_OPEN);
+        sink.Close()?;
+        Ok(path)
+    }
+}
+
+/// Path geometry of a `DrawOperation::Arc`, angles in degrees clockwise from the positive X axis
+///
+/// The sweep is split into two halves so that neither half exceeds 180 degrees; a single
+/// segment can't describe a full circle, its start and end point would coincide.
+pub(crate) fn arc_geometry(
+    factory: &ID2D1Factory,
+    center: Vector2,
+    radius: f32,
+    start_angle: f32,
+    sweep_angle: f32,
+) -> Result<ID2D1PathGeometry> {
+    let sweep_angle = sweep_angle.clamp(-360.0, 360.0);
+    let point_at = |angle: f32| {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Vector2 {
+            X: center.X + radius * cos,
+            Y: center.Y + radius * sin,
+        }
+    };
+    let sweep_direction = if sweep_angle >= 0.0 {
+        D2D1_SWEEP_DIRECTION_CLOCKWISE
+    } else {
+        D2D1_SWEEP_DIRECTION_COUNTER_CLOCKWISE
+    };
+    unsafe {
+        let path = factory.CreatePathGeometry()?;
+        let sink = path.Open()?;
+        sink.BeginFigure(point_at(start_angle), D2D1_FIGURE_BEGIN_HOLLOW);
+        for half in 1..=2 {
+            sink.AddArc(&D2D1_ARC_SEGMENT {
+                point: point_at(start_angle + sweep_angle * half as f32 / 2.0),
+                size: D2D_SIZE_F {
+                    width: radius,
+                    height: radius,
+                },
+                rotationAngle: 0.0,
+                sweepDirection: sweep_direction,
+                arcSize: D2D1_ARC_SIZE_SMALL,
+            });
+        }
+        sink.EndFigure(D2D1_FIGURE_END_OPEN);
+        sink.Close()?;
+        Ok(path)
+    }
+}
+
+/// Pixels per DIP along each axis of the intermediate bitmap when supersampling
+const SUPERSAMPLING_SCALE: u32 = 2;
+
+/// DPI reported by renderers without a window
+const HEADLESS_DPI: u32 = 96;
+
+/// Longest wait for the swap chain to take another frame, so a stalled compositor (e.g. a
+/// locked session) doesn't hold up the message loop
+const FRAME_LATENCY_TIMEOUT_MS: u32 = 100;
+
 #[allow(dead_code)]
 impl Renderer {
     /// Select the best adapter for wallpaper rendering (prefer integrated GPU for power efficiency)
@@ -160,32 +344,84 @@ impl Renderer {
     /// * `width` - Initial width
     /// * `height` - Initial height
     /// * `enable_vsync` - Enable vsync (true = lock to display refresh rate, false = unlocked)
-    pub fn new(hwnd: HWND, width: u32, height: u32, enable_vsync: bool) -> Result<Self> {
-        unsafe {
-            // Step 1: Select best adapter for wallpaper use
-            let adapter = Self::select_adapter()?;
-            let desc = adapter.GetDesc1()?;
-            let adapter_name = String::from_utf16_lossy(&desc.Description);
-            let adapter_name = adapter_name.trim_end_matches('\0');
-            info!("Using GPU adapter: {}", adapter_name);
-
-            // Step 2: Create D3D11 device (Direct2D requires this)
-            let mut device: Option<ID3D11Device> = None;
-            let mut context: Option<ID3D11DeviceContext> = None;
-            let mut feature_level: D3D_FEATURE_LEVEL = D3D_FEATURE_LEVEL_11_0;
-
-            // Enable debug layer in debug builds for better validation and error messages
-            let mut device_flags = D3D11_CREATE_DEVICE_BGRA_SUPPORT;
-            if cfg!(debug_assertions) {
-                device_flags |= D3D11_CREATE_DEVICE_DEBUG;
-                debug!("D3D11 debug layer enabled");
-            }
+    /// * `swap_chain_config` - Buffer count and presentation model of the swap chain
+    pub fn new(
+        hwnd: HWND,
+        width: u32,
+        height: u32,
+        enable_vsync: bool,
+        swap_chain_config: &SwapChainConfig,
+    ) -> Result<Self> {
+        swap_chain_config.validate()?;
+
+        // Step 1: Select best adapter for wallpaper use
+        let adapter = Self::select_adapter()?;
+        let desc = unsafe { adapter.GetDesc1()? };
+        let adapter_name = String::from_utf16_lossy(&desc.Description);
+        let adapter_name = adapter_name.trim_end_matches('\0').to_string();
+        info!("Using GPU adapter: {}", adapter_name);
+
+        // Step 2: Create D3D11 device (Direct2D requires this)
+        // Must use UNKNOWN when providing an adapter
+        let (d3d_device, d3d_context) =
+            Self::create_d3d_device(Some(&*adapter), D3D_DRIVER_TYPE_UNKNOWN)?;
+
+        let renderer = Self::with_device(
+            d3d_device,
+            d3d_context,
+            Some(hwnd),
+            width,
+            height,
+            enable_vsync,
+            swap_chain_config,
+            adapter_name,
+        )?;
+        if swap_chain_config.pre_clear {
+            renderer.pre_clear_buffers(swap_chain_config.buffer_count)?;
+        }
+        Ok(renderer)
+    }
+
+    /// Create a renderer without a window on the WARP software rasterizer
+    ///
+    /// Draws into a plain bitmap instead of a swap chain, end_draw() finishes the frame
+    /// without presenting it. Use `read_pixels` to get the result.
+    pub fn new_headless(width: u32, height: u32) -> Result<Self> {
+        let (d3d_device, d3d_context) = Self::create_d3d_device(None, D3D_DRIVER_TYPE_WARP)?;
+        Self::with_device(
+            d3d_device,
+            d3d_context,
+            None,
+            width,
+            height,
+            false,
+            &SwapChainConfig::default(),
+            "WARP".to_string(),
+        )
+    }
+
+    /// Create the D3D11 device, on `adapter` or on the given driver type
+    fn create_d3d_device(
+        adapter: Option<&IDXGIAdapter>,
+        driver_type: D3D_DRIVER_TYPE,
+    ) -> Result<(ID3D11Device, ID3D11DeviceContext)> {
+        let mut device: Option<ID3D11Device> = None;
+        let mut context: Option<ID3D11DeviceContext> = None;
+        let mut feature_level: D3D_FEATURE_LEVEL = D3D_FEATURE_LEVEL_11_0;
+
+        // Enable debug layer in debug builds for better validation and error messages
+        let mut device_flags = D3D11_CREATE_DEVICE_BGRA_SUPPORT;
+        if cfg!(debug_assertions) {
+            device_flags |= D3D11_CREATE_DEVICE_DEBUG;
+            debug!("D3D11 debug layer enabled");
+        }
 
-            // Try feature levels in descending order: 11.1, 11.0, 10.1, 10.0
-            // This provides broader hardware compatibility
+        // Try feature levels in descending order: 11.1, 11.0, 10.1, 10.0
+        // This provides broader hardware compatibility
+        unsafe {
             D3D11CreateDevice(
-                &adapter,
-                D3D_DRIVER_TYPE_UNKNOWN, // Must use UNKNOWN when providing an adapter
+                adapter,
+                driver_type,
                 Default::default(),
                 device_flags,
                 Some(&[
@@ -200,20 +436,38 @@ impl Renderer {
                 Some(&mut context as *mut _),
             )
             .context("Failed to create D3D11 device")?;
+        }
 
-            let d3d_device = device.context("D3D11 device is None")?;
-            let d3d_context = context.context("D3D11 context is None")?;
+        // Log the selected feature level
+        let feature_level_str = match feature_level {
+            D3D_FEATURE_LEVEL_11_1 => "11.1",
+            D3D_FEATURE_LEVEL_11_0 => "11.0",
+            D3D_FEATURE_LEVEL_10_1 => "10.1",
+            D3D_FEATURE_LEVEL_10_0 => "10.0",
+            _ => "Unknown",
+        };
+        info!("Direct3D Feature Level: {}", feature_level_str);
 
-            // Log the selected feature level
-            let feature_level_str = match feature_level {
-                D3D_FEATURE_LEVEL_11_1 => "11.1",
-                D3D_FEATURE_LEVEL_11_0 => "11.0",
-                D3D_FEATURE_LEVEL_10_1 => "10.1",
-                D3D_FEATURE_LEVEL_10_0 => "10.0",
-                _ => "Unknown",
-            };
-            info!("Direct3D Feature Level: {}", feature_level_str);
+        Ok((
+            device.context("D3D11 device is None")?,
+            context.context("D3D11 context is None")?,
+        ))
+    }
 
+    /// Build the Direct2D/DirectWrite side on top of a D3D11 device, presenting to `hwnd`
+    /// through a composition swap chain, or into a plain bitmap when there is no window
+    #[allow(clippy::too_many_arguments)]
+    fn with_device(
+        d3d_device: ID3D11Device,
+        d3d_context: ID3D11DeviceContext,
+        hwnd: Option<HWND>,
+        width: u32,
+        height: u32,
+        enable_vsync: bool,
+        swap_chain_config: &SwapChainConfig,
+        adapter_name: String,
+    ) -> Result<Self> {
+        unsafe {
             // Step 3: Get DXGI device
             let dxgi_device: IDXGIDevice = d3d_device
                 .cast::<IDXGIDevice>()
@@ -234,6 +488,116 @@ impl Renderer {
                 .CreateDeviceContext(D2D1_DEVICE_CONTEXT_OPTIONS_NONE)
                 .context("Failed to create Direct2D device context")?;
 
+            // Steps 7-9, 11-14: swap chain and composition tree, or an offscreen target
+            let (d2d_bitmap, presentation) = match hwnd {
+                Some(hwnd) => {
+                    let (d2d_bitmap, presentation) = Self::create_presentation(
+                        &dxgi_device,
+                        &d2d_context,
+                        hwnd,
+                        width,
+                        height,
+                        enable_vsync,
+                        swap_chain_config,
+                    )?;
+                    (d2d_bitmap, Some(presentation))
+                }
+                None => (
+                    Self::create_headless_target(&d2d_context, width, height)?,
+                    None,
+                ),
+            };
+
+            // Set the target bitmap as the initial render target
+            d2d_context.SetTarget(&d2d_bitmap);
+
+            // Extract underlying D3D11 texture for efficient GPU-level copying
+            let swap_chain_texture = Self::target_texture(&d2d_bitmap)?;
+
+            // Step 10: Create DirectWrite factory
+            let dwrite_factory: IDWriteFactory = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)
+                .context("Failed to create DirectWrite factory")?;
+
+            // Step 15: Create stroke style with flat caps for pixel-perfect lines
+            let stroke_props = D2D1_STROKE_STYLE_PROPERTIES1 {
+                startCap: D2D1_CAP_STYLE_FLAT,
+                endCap: D2D1_CAP_STYLE_FLAT,
+                dashCap: D2D1_CAP_STYLE_FLAT,
+                ..Default::default()
+            };
+            let flat_cap_stroke_style: ID2D1StrokeStyle =
+                d2d_factory.CreateStrokeStyle(&stroke_props, None)?.into();
+
+            let round_join_stroke_props = D2D1_STROKE_STYLE_PROPERTIES1 {
+                lineJoin: D2D1_LINE_JOIN_ROUND,
+                ..stroke_props
+            };
+            let round_join_stroke_style: ID2D1StrokeStyle = d2d_factory
+                .CreateStrokeStyle(&round_join_stroke_props, None)?
+                .into();
+
+            let sync_interval = if enable_vsync {
+                debug!("VSync enabled (sync_interval = 1)");
+                1
+            } else {
+                debug!("VSync disabled (sync_interval = 0) for maximum frame rate");
+                0
+            };
+
+            Ok(Self {
+                d3d_device,
+                d3d_context,
+                d2d_factory,
+                d2d_device,
+                d2d_context,
+                d2d_bitmap,
+                intermediate_bitmap: None,
+                previous_frame_bitmap: None,
+                intermediate_scale: 1,
+                resolved_bitmap: None,
+                swap_chain_texture,
+                intermediate_texture: None,
+                resolved_texture: None,
+                dwrite_factory,
+                presentation,
+                overlay_operations: RefCell::new(Vec::new()),
+                text_overlay: None,
+                static_overlay: None,
+                overlay_on_screen: Cell::new(false),
+                brush_cache: RefCell::new(HashMap::new()),
+                gradient_brush_cache: RefCell::new(HashMap::new()),
+                flat_cap_stroke_style,
+                round_join_stroke_style,
+                round_joins: false,
+                antialias: false,
+                background: None,
+                background_brush: None,
+                erasing: Cell::new(false),
+                swap_chain_config: swap_chain_config.clone(),
+                sync_interval,
+                present_opacity: 1.0,
+                dirty: Cell::new(DirtyRegion::Full),
+                motion_blur: 0.0,
+                global_alpha: 1.0,
+                width,
+                height,
+                dpi: hwnd.map_or(HEADLESS_DPI, |hwnd| GetDpiForWindow(hwnd)),
+                adapter_name,
+            })
+        }
+    }
+
+    /// Create the composition swap chain for `hwnd` and the Direct2D bitmap of its back buffer
+    fn create_presentation(
+        dxgi_device: &IDXGIDevice,
+        d2d_context: &ID2D1DeviceContext,
+        hwnd: HWND,
+        width: u32,
+        height: u32,
+        enable_vsync: bool,
+        swap_chain_config: &SwapChainConfig,
+    ) -> Result<(ID2D1Bitmap1, Presentation)> {
+        unsafe {
             // Step 7: Get DXGI adapter and factory
             let adapter = dxgi_device
                 .GetAdapter()
@@ -241,6 +605,15 @@ impl Renderer {
             let factory: IDXGIFactory2 =
                 adapter.GetParent().context("Failed to get DXGI factory")?;
 
+            // Without vsync, let presents tear instead of blocking if the system supports it
+            let allow_tearing = !enable_vsync && Self::tearing_supported(&factory);
+            if allow_tearing {
+                debug!("Tearing presents enabled");
+            }
+
+            // Pacing against the display only makes sense when presents wait for it
+            let frame_pacing = enable_vsync && swap_chain_config.frame_pacing;
+
             // Step 8: Create composition swap chain
             let swap_chain_desc = DXGI_SWAP_CHAIN_DESC1 {
                 Width: width,
@@ -252,53 +625,35 @@ impl Renderer {
                     Quality: 0,
                 },
                 BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
-                BufferCount: 2,
+                BufferCount: swap_chain_config.buffer_count,
                 Scaling: DXGI_SCALING_STRETCH,
-                SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
+                SwapEffect: swap_chain_config.swap_effect.into(),
                 AlphaMode: DXGI_ALPHA_MODE_PREMULTIPLIED,
-                Flags: 0,
+                Flags: Self::swap_chain_flags(allow_tearing, frame_pacing).0 as u32,
             };
 
             let swap_chain: IDXGISwapChain1 = factory
-                .CreateSwapChainForComposition(&dxgi_device, &swap_chain_desc, None)
+                .CreateSwapChainForComposition(dxgi_device, &swap_chain_desc, None)
                 .context("Failed to create composition swap chain")?;
 
-            // Step 9: Create Direct2D bitmap from swap chain buffer
-            let dxgi_surface: IDXGISurface = swap_chain
-                .GetBuffer(0)
-                .context("Failed to get swap chain buffer")?;
-
-            let bitmap_properties = D2D1_BITMAP_PROPERTIES1 {
-                pixelFormat: D2D1_PIXEL_FORMAT {
-                    format: DXGI_FORMAT_B8G8R8A8_UNORM,
-                    alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
-                },
-                dpiX: 96.0,
-                dpiY: 96.0,
-                bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
-                colorContext: ManuallyDrop::new(None),
+            let frame_latency_object = if frame_pacing {
+                let swap_chain: IDXGISwapChain2 = swap_chain
+                    .cast()
+                    .context("Failed to get IDXGISwapChain2 for frame pacing")?;
+                swap_chain
+                    .SetMaximumFrameLatency(1)
+                    .context("Failed to set maximum frame latency")?;
+                debug!("Frame pacing on the frame latency waitable object");
+                Some(swap_chain.GetFrameLatencyWaitableObject())
+            } else {
+                None
             };
 
-            let d2d_bitmap: ID2D1Bitmap1 = d2d_context
-                .CreateBitmapFromDxgiSurface(&dxgi_surface, Some(&bitmap_properties))
-                .context("Failed to create Direct2D bitmap from DXGI surface")?;
-
-            // Set the swap chain bitmap as the initial render target
-            d2d_context.SetTarget(&d2d_bitmap);
-
-            // Extract underlying D3D11 texture for efficient GPU-level copying
-            let swap_chain_texture: ID3D11Texture2D = d2d_bitmap
-                .GetSurface()
-                .context("Failed to get surface from swap chain bitmap")?
-                .cast::<ID3D11Texture2D>()
-                .context("Failed to cast surface to ID3D11Texture2D")?;
-
-            // Step 10: Create DirectWrite factory
-            let dwrite_factory: IDWriteFactory = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)
-                .context("Failed to create DirectWrite factory")?;
+            // Step 9: Create Direct2D bitmap from swap chain buffer
+            let d2d_bitmap = Self::back_buffer_bitmap(d2d_context, &swap_chain)?;
 
             // Step 11: Create DirectComposition device
-            let composition_device: IDCompositionDevice = DCompositionCreateDevice(&dxgi_device)
+            let composition_device: IDCompositionDevice = DCompositionCreateDevice(dxgi_device)
                 .context("Failed to create DirectComposition device")?;
 
             // Step 12: Create composition target
@@ -324,48 +679,224 @@ impl Renderer {
                 .Commit()
                 .context("Failed to commit composition changes")?;
 
-            // Step 15: Create stroke style with flat caps for pixel-perfect lines
-            let stroke_props = D2D1_STROKE_STYLE_PROPERTIES1 {
-                startCap: D2D1_CAP_STYLE_FLAT,
-                endCap: D2D1_CAP_STYLE_FLAT,
-                dashCap: D2D1_CAP_STYLE_FLAT,
-                ..Default::default()
-            };
-            let flat_cap_stroke_style: ID2D1StrokeStyle =
-                d2d_factory.CreateStrokeStyle(&stroke_props, None)?.into();
+            Ok((
+                d2d_bitmap,
+                Presentation {
+                    swap_chain,
+                    allow_tearing,
+                    frame_latency_object,
+                    frame_slot: Cell::new(false),
+                    composition_device,
+                    _composition_target: composition_target,
+                    _composition_visual: composition_visual,
+                },
+            ))
+        }
+    }
 
-            let sync_interval = if enable_vsync {
-                debug!("VSync enabled (sync_interval = 1)");
-                1
-            } else {
-                debug!("VSync disabled (sync_interval = 0) for maximum frame rate");
-                0
-            };
+    /// Whether the display stack supports presents with DXGI_PRESENT_ALLOW_TEARING
+    fn tearing_supported(factory: &IDXGIFactory2) -> bool {
+        let Ok(factory) = factory.cast::<IDXGIFactory5>() else {
+            return false;
+        };
+        let mut supported = BOOL(0);
+        unsafe {
+            factory
+                .CheckFeatureSupport(
+                    DXGI_FEATURE_PRESENT_ALLOW_TEARING,
+                    &mut supported as *mut BOOL as *mut _,
+                    size_of::<BOOL>() as u32,
+                )
+                .is_ok()
+                && supported.as_bool()
+        }
+    }
 
-            Ok(Self {
-                d3d_device,
-                d3d_context,
-                d2d_factory,
-                d2d_device,
-                d2d_context,
-                d2d_bitmap,
-                intermediate_bitmap: None,
-                swap_chain_texture,
-                intermediate_texture: None,
-                dwrite_factory,
-                swap_chain,
-                composition_device,
-                _composition_target: composition_target,
-                _composition_visual: composition_visual,
-                brush_cache: RefCell::new(HashMap::new()),
-                flat_cap_stroke_style,
-                sync_interval,
-                width,
-                height,
-            })
+    /// Creation flags of the swap chain, ResizeBuffers has to be passed the same ones
+    fn swap_chain_flags(allow_tearing: bool, frame_pacing: bool) -> DXGI_SWAP_CHAIN_FLAG {
+        let mut flags = DXGI_SWAP_CHAIN_FLAG(0);
+        if allow_tearing {
+            flags |= DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING;
+        }
+        if frame_pacing {
+            flags |= DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT;
+        }
+        flags
+    }
+
+    /// Direct2D bitmap of the swap chain's back buffer
+    fn back_buffer_bitmap(
+        d2d_context: &ID2D1DeviceContext,
+        swap_chain: &IDXGISwapChain1,
+    ) -> Result<ID2D1Bitmap1> {
+        // Kept at 96 DPI whatever the monitor's DPI is: one DIP is one physical pixel, so
+        // the pixel sizes scenes draw with (and aliased line widths) stay exact
+        let bitmap_properties = D2D1_BITMAP_PROPERTIES1 {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.0,
+            dpiY: 96.0,
+            bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+            colorContext: ManuallyDrop::new(None),
+        };
+        unsafe {
+            let dxgi_surface: IDXGISurface = swap_chain
+                .GetBuffer(0)
+                .context("Failed to get swap chain buffer")?;
+            d2d_context
+                .CreateBitmapFromDxgiSurface(&dxgi_surface, Some(&bitmap_properties))
+                .context("Failed to create Direct2D bitmap from DXGI surface")
+        }
+    }
+
+    /// D3D11 texture behind the target bitmap, for GPU-level copies into it
+    fn target_texture(bitmap: &ID2D1Bitmap1) -> Result<ID3D11Texture2D> {
+        unsafe {
+            bitmap
+                .GetSurface()
+                .context("Failed to get surface from swap chain bitmap")?
+                .cast::<ID3D11Texture2D>()
+                .context("Failed to cast surface to ID3D11Texture2D")
         }
     }
 
+    /// Resize the target to `width`x`height`, keeping the devices and the composition tree
+    ///
+    /// Much cheaper than building a new renderer and avoids a flash of the window, but
+    /// everything drawn so far is lost: incremental rendering continues on a new, black
+    /// intermediate bitmap and the scene has to redraw.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<()> {
+        if (width, height) == (self.width, self.height) {
+            return Ok(());
+        }
+        debug!("Resizing renderer to {}x{}", width, height);
+
+        let was_incremental = self.is_incremental();
+        self.non_incremental();
+        unsafe {
+            self.d2d_context.SetTarget(None);
+            match &self.presentation {
+                Some(presentation) => {
+                    // ResizeBuffers fails while anything still references the old buffers,
+                    // park a tiny bitmap in their place until the new back buffer exists
+                    self.d2d_bitmap = Self::create_headless_target(&self.d2d_context, 1, 1)?;
+                    self.swap_chain_texture = Self::target_texture(&self.d2d_bitmap)?;
+                    self.d3d_context.ClearState();
+                    self.d3d_context.Flush();
+
+                    presentation
+                        .swap_chain
+                        .ResizeBuffers(
+                            0,
+                            width,
+                            height,
+                            DXGI_FORMAT_UNKNOWN,
+                            Self::swap_chain_flags(
+                                presentation.allow_tearing,
+                                presentation.frame_latency_object.is_some(),
+                            ),
+                        )
+                        .context("Failed to resize swap chain buffers")?;
+                    self.d2d_bitmap =
+                        Self::back_buffer_bitmap(&self.d2d_context, &presentation.swap_chain)?;
+                }
+                None => {
+                    self.d2d_bitmap =
+                        Self::create_headless_target(&self.d2d_context, width, height)?;
+                }
+            }
+            self.swap_chain_texture = Self::target_texture(&self.d2d_bitmap)?;
+            self.d2d_context.SetTarget(&self.d2d_bitmap);
+        }
+        self.width = width;
+        self.height = height;
+        self.fit_background_brush();
+        self.mark_dirty();
+
+        if was_incremental {
+            self.incremental_no_copy()?;
+        }
+        Ok(())
+    }
+
+    /// Render target bitmap standing in for the swap chain buffer of a headless renderer
+    fn create_headless_target(
+        d2d_context: &ID2D1DeviceContext,
+        width: u32,
+        height: u32,
+    ) -> Result<ID2D1Bitmap1> {
+        let bitmap_properties = D2D1_BITMAP_PROPERTIES1 {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.0,
+            dpiY: 96.0,
+            bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET,
+            colorContext: ManuallyDrop::new(None),
+        };
+        unsafe {
+            d2d_context
+                .CreateBitmap(D2D_SIZE_U { width, height }, None, 0, &bitmap_properties)
+                .context("Failed to create headless target bitmap")
+        }
+    }
+
+    /// Build a fresh renderer for `hwnd` with the size and settings of this one,
+    /// e.g. after `RendererError::DeviceLost` made every device resource unusable
+    pub fn recreate(self, hwnd: HWND) -> Result<Self> {
+        let (width, height) = (self.width, self.height);
+        let enable_vsync = self.sync_interval > 0;
+        let swap_chain_config = self.swap_chain_config.clone();
+        // Release the lost device before creating the new one
+        drop(self);
+
+        info!("Recreating renderer ({}x{})", width, height);
+        Self::new(hwnd, width, height, enable_vsync, &swap_chain_config)
+    }
+
+    /// Turn a device removed/reset HRESULT into `RendererError::DeviceLost`
+    fn check_device_lost(&self, code: HRESULT) -> Result<()> {
+        if code == DXGI_ERROR_DEVICE_REMOVED
+            || code == DXGI_ERROR_DEVICE_RESET
+            || code == D2DERR_RECREATE_TARGET
+        {
+            let reason = unsafe { self.d3d_device.GetDeviceRemovedReason() }
+                .err()
+                .map_or(S_OK, |error| error.code());
+            return Err(RendererError::DeviceLost { code, reason }.into());
+        }
+        Ok(())
+    }
+
+    /// Clear every back buffer of the swap chain to black and present it once
+    ///
+    /// Flip model swap chains always expose the current back buffer as buffer 0, so presenting
+    /// `buffer_count` times walks through all of them.
+    fn pre_clear_buffers(&self, buffer_count: u32) -> Result<()> {
+        let Some(presentation) = &self.presentation else {
+            return Ok(());
+        };
+        for _ in 0..buffer_count {
+            self.begin_draw();
+            self.clear(D2D1_COLOR_F::black());
+            unsafe {
+                self.d2d_context
+                    .EndDraw(None, None)
+                    .context("Failed to pre-clear swap chain buffer")?;
+                presentation
+                    .swap_chain
+                    .Present(0, DXGI_PRESENT(0))
+                    .ok()
+                    .context("Failed to present pre-cleared swap chain buffer")?;
+            }
+        }
+        debug!("Pre-cleared {} swap chain buffers", buffer_count);
+        Ok(())
+    }
+
     /// Get the Direct2D device context for drawing
     pub fn context(&self) -> &ID2D1DeviceContext {
         &self.d2d_context
@@ -381,26 +912,174 @@ impl Renderer {
         (self.width, self.height)
     }
 
+    /// Get the DPI of the monitor the window is shown on
+    pub fn dpi(&self) -> u32 {
+        self.dpi
+    }
+
+    /// Update the DPI after the window moved to another monitor. Drawing is in physical
+    /// pixels either way, nothing has to be recreated.
+    pub fn set_dpi(&mut self, dpi: u32) {
+        self.dpi = dpi;
+    }
+
+    /// Get the name of the GPU adapter used for rendering
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// Block until the swap chain can take another frame when frame pacing is on, so the
+    /// frame starts right after the previous one was shown. Returns at once otherwise, or
+    /// when the frame from the last wait hasn't been presented yet.
+    pub fn wait_for_frame(&self) {
+        let Some(presentation) = &self.presentation else {
+            return;
+        };
+        if let Some(handle) = presentation.frame_latency_object
+            && !presentation.frame_slot.get()
+        {
+            let result = unsafe { WaitForSingleObjectEx(handle, FRAME_LATENCY_TIMEOUT_MS, true) };
+            presentation.frame_slot.set(result == WAIT_OBJECT_0);
+        }
+    }
+
     /// Begin a rendering frame
     pub fn begin_draw(&self) {
         unsafe {
             self.d2d_context.BeginDraw();
-            // Disable antialiasing for pixel-perfect rendering
-            self.d2d_context
-                .SetAntialiasMode(D2D1_ANTIALIAS_MODE_ALIASED);
+            // Antialiasing is off by default for pixel-perfect rendering
+            self.d2d_context.SetAntialiasMode(self.antialias_mode());
             // Reset to normal blend mode (in case it was changed for erasure)
             self.d2d_context
                 .SetPrimitiveBlend(D2D1_PRIMITIVE_BLEND_SOURCE_OVER);
         }
+        self.erasing.set(false);
     }
 
     /// Clear the render target with a color
     pub fn clear(&self, color: D2D1_COLOR_F) {
+        self.mark_dirty();
         unsafe {
             self.d2d_context.Clear(Some(&color));
         }
     }
 
+    /// Decode an image file with WIC and use it as the background, replacing any previous one
+    ///
+    /// The image is stretched to the target size, so its aspect ratio is only kept if it
+    /// matches the screen.
+    pub fn load_background(&mut self, path: &Path) -> Result<()> {
+        let wic_factory = create_wic_factory()?;
+        unsafe {
+            let decoder = wic_factory
+                .CreateDecoderFromFilename(
+                    &HSTRING::from(path),
+                    None,
+                    GENERIC_READ,
+                    WICDecodeMetadataCacheOnDemand,
+                )
+                .with_context(|| format!("Failed to open background image {}", path.display()))?;
+            let frame = decoder
+                .GetFrame(0)
+                .context("Failed to decode background image")?;
+
+            // Direct2D only takes premultiplied BGRA
+            let converter = wic_factory
+                .CreateFormatConverter()
+                .context("Failed to create WIC format converter")?;
+            converter
+                .Initialize(
+                    &frame,
+                    &GUID_WICPixelFormat32bppPBGRA,
+                    WICBitmapDitherTypeNone,
+                    None::<&IWICPalette>,
+                    0.0,
+                    WICBitmapPaletteTypeMedianCut,
+                )
+                .context("Failed to convert background image")?;
+
+            let bitmap = self
+                .d2d_context
+                .CreateBitmapFromWicBitmap(&converter, None)
+                .context("Failed to create background bitmap")?;
+            let brush = self
+                .d2d_context
+                .CreateBitmapBrush(&bitmap, None, None)
+                .context("Failed to create background brush")?;
+
+            let size = bitmap.GetPixelSize();
+            debug!(
+                "Loaded background {} ({}x{})",
+                path.display(),
+                size.width,
+                size.height
+            );
+            self.background = Some(bitmap);
+            self.background_brush = Some(brush);
+        }
+        self.fit_background_brush();
+        Ok(())
+    }
+
+    pub fn has_background(&self) -> bool {
+        self.background.is_some()
+    }
+
+    /// Go back to clearing to the background color
+    pub fn unload_background(&mut self) {
+        self.background = None;
+        self.background_brush = None;
+    }
+
+    /// Stretch the background brush over the whole target, like `draw_background`
+    fn fit_background_brush(&self) {
+        if let (Some(background), Some(brush)) = (&self.background, &self.background_brush) {
+            unsafe {
+                let size = background.GetSize();
+                brush.SetTransform(&Matrix3x2::scale(
+                    self.width as f32 / size.width,
+                    self.height as f32 / size.height,
+                ));
+            }
+        }
+    }
+
+    /// Fill the render target with the background image, stretched to the target size.
+    /// Clears to the background color instead if no background is loaded.
+    pub fn draw_background(&self) {
+        let Some(background) = &self.background else {
+            self.clear(self.background_color());
+            return;
+        };
+        self.mark_dirty();
+        let target = D2D_RECT_F {
+            left: 0.0,
+            top: 0.0,
+            right: self.width as f32,
+            bottom: self.height as f32,
+        };
+        unsafe {
+            self.d2d_context.DrawBitmap(
+                background,
+                Some(&target),
+                1.0,
+                D2D1_INTERPOLATION_MODE_LINEAR,
+                None,
+                None,
+            );
+        }
+    }
+
+    /// Color behind everything drawn: black, or transparent black when the frame is presented
+    /// with a global alpha so the desktop shows through
+    pub fn background_color(&self) -> D2D1_COLOR_F {
+        if self.global_alpha < 1.0 {
+            D2D1_COLOR_F::black().with_alpha(0.0)
+        } else {
+            D2D1_COLOR_F::black()
+        }
+    }
+
     pub fn is_incremental(&self) -> bool {
         self.intermediate_bitmap.is_some()
     }
@@ -416,30 +1095,362 @@ impl Renderer {
     /// - Without incremental: Full scene redraw every frame (~20% GPU usage)
     /// - With incremental: Only draw new content each frame (~1% GPU usage)
     ///
-    /// The intermediate bitmap is copied to the swap chain during end_draw() for presentation.
-    /// Uses NEAREST_NEIGHBOR interpolation for efficient 1:1 pixel-aligned copy.
-    pub fn incremental_no_copy(&mut self) -> Result<()> {
-        self.incremental_with_copy(false)
+    /// The intermediate bitmap is copied to the swap chain during end_draw() for presentation.
+    /// Uses NEAREST_NEIGHBOR interpolation for efficient 1:1 pixel-aligned copy.
+    pub fn incremental_no_copy(&mut self) -> Result<()> {
+        self.incremental_with_copy(false)
+    }
+
+    fn incremental_with_copy(&mut self, copy_existing: bool) -> Result<()> {
+        if self.is_incremental() {
+            return Ok(());
+        }
+
+        // A supersampled bitmap keeps the scene's coordinates through its DPI: one DIP covers
+        // `scale` pixels, so drawing code is unaware of the higher resolution
+        let scale = self.supersampling_scale();
+        let intermediate_bitmap = self
+            .create_target_bitmap(self.width * scale, self.height * scale, scale)
+            .context("Failed to create intermediate bitmap")?;
+        let resolved_bitmap = if scale > 1 {
+            let bitmap = self
+                .create_target_bitmap(self.width, self.height, 1)
+                .context("Failed to create resolved bitmap")?;
+            let texture = Self::target_texture(&bitmap)?;
+            Some((bitmap, texture))
+        } else {
+            None
+        };
+
+        // Copy current swap chain content to intermediate bitmap if requested
+        // Only copy if there's existing content to preserve (e.g., after reverse animation)
+        // Supersampled, it goes through the resolved bitmap first: the swap chain buffer is
+        // bound as a target and can't be drawn, and bitmaps of different sizes can't be copied
+        let copy_target = match &resolved_bitmap {
+            Some((bitmap, _)) => bitmap,
+            None => &intermediate_bitmap,
+        };
+        if copy_existing {
+            unsafe {
+                let src_rect = windows::Win32::Graphics::Direct2D::Common::D2D_RECT_U {
+                    left: 0,
+                    top: 0,
+                    right: self.width,
+                    bottom: self.height,
+                };
+                let dest_point =
+                    windows::Win32::Graphics::Direct2D::Common::D2D_POINT_2U { x: 0, y: 0 };
+
+                copy_target
+                    .CopyFromBitmap(Some(&dest_point), &self.d2d_bitmap, Some(&src_rect))
+                    .context("Failed to copy swap chain content to intermediate bitmap")?;
+            }
+        }
+
+        // Switch render target to intermediate bitmap
+        unsafe {
+            self.d2d_context.SetTarget(&intermediate_bitmap);
+            self.d2d_context
+                .SetDpi(96.0 * scale as f32, 96.0 * scale as f32);
+        }
+
+        // A freshly created bitmap holds undefined memory, start from the background color
+        // so a frame presented before the scene clears never shows garbage
+        if !copy_existing {
+            unsafe {
+                self.d2d_context.BeginDraw();
+                self.d2d_context.Clear(Some(&self.background_color()));
+                self.d2d_context
+                    .EndDraw(None, None)
+                    .context("Failed to clear intermediate bitmap")?;
+            }
+        } else if let Some((resolved_bitmap, _)) = &resolved_bitmap {
+            // Scale the copied swap chain content up
+            unsafe {
+                self.d2d_context.BeginDraw();
+                self.d2d_context.DrawBitmap(
+                    resolved_bitmap,
+                    None,
+                    1.0,
+                    D2D1_INTERPOLATION_MODE_LINEAR,
+                    None,
+                    None,
+                );
+                self.d2d_context
+                    .EndDraw(None, None)
+                    .context("Failed to copy swap chain content to intermediate bitmap")?;
+            }
+        }
+
+        // Extract underlying D3D11 texture for efficient GPU-level copying
+        let intermediate_texture: ID3D11Texture2D = unsafe {
+            intermediate_bitmap
+                .GetSurface()
+                .context("Failed to get surface from intermediate bitmap")?
+                .cast::<ID3D11Texture2D>()
+                .context("Failed to cast surface to ID3D11Texture2D")?
+        };
+
+        self.intermediate_bitmap = Some(intermediate_bitmap);
+        self.intermediate_texture = Some(intermediate_texture);
+        self.intermediate_scale = scale;
+        (self.resolved_bitmap, self.resolved_texture) = resolved_bitmap.unzip();
+
+        Ok(())
+    }
+
+    pub fn non_incremental(&mut self) {
+        if !self.is_incremental() {
+            return;
+        }
+
+        // Clear intermediate bitmap and switch back to swap chain bitmap
+        self.target_back_buffer();
+        self.intermediate_bitmap = None;
+        self.intermediate_texture = None;
+        self.intermediate_scale = 1;
+        self.resolved_bitmap = None;
+        self.resolved_texture = None;
+        self.previous_frame_bitmap = None;
+    }
+
+    /// Resolution multiplier for the intermediate bitmap: `SUPERSAMPLING_SCALE` when
+    /// supersampling is configured and the device can hold a bitmap that large, 1 otherwise
+    fn supersampling_scale(&self) -> u32 {
+        if !self.swap_chain_config.supersampling {
+            return 1;
+        }
+        let max_size = unsafe { self.d2d_context.GetMaximumBitmapSize() };
+        if self.width.max(self.height) * SUPERSAMPLING_SCALE > max_size {
+            warn!(
+                max_size,
+                "Supersampled scene would exceed the largest bitmap the device supports, \
+                 drawing at native resolution"
+            );
+            return 1;
+        }
+        SUPERSAMPLING_SCALE
+    }
+
+    /// Bitmap that can be drawn into and drawn, `scale` pixels per DIP
+    fn create_target_bitmap(&self, width: u32, height: u32, scale: u32) -> Result<ID2D1Bitmap1> {
+        let bitmap_properties = D2D1_BITMAP_PROPERTIES1 {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.0 * scale as f32,
+            dpiY: 96.0 * scale as f32,
+            bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET,
+            colorContext: ManuallyDrop::new(None),
+        };
+        unsafe {
+            Ok(self.d2d_context.CreateBitmap(
+                D2D_SIZE_U { width, height },
+                None,
+                0,
+                &bitmap_properties,
+            )?)
+        }
+    }
+
+    /// Draw into the swap chain buffer, one DIP per pixel
+    fn target_back_buffer(&self) {
+        unsafe {
+            self.d2d_context.SetTarget(&self.d2d_bitmap);
+            self.d2d_context.SetDpi(96.0, 96.0);
+        }
+    }
+
+    /// Draw into the intermediate bitmap again after drawing elsewhere
+    fn restore_intermediate_target(&self) {
+        if let Some(intermediate_bitmap) = &self.intermediate_bitmap {
+            let dpi = 96.0 * self.intermediate_scale as f32;
+            unsafe {
+                self.d2d_context.SetTarget(intermediate_bitmap);
+                self.d2d_context.SetDpi(dpi, dpi);
+            }
+        }
+    }
+
+    /// The scene at the target size: the resolved bitmap when supersampling, the intermediate
+    /// bitmap otherwise, None when not rendering incrementally
+    fn scene_bitmap(&self) -> Option<&ID2D1Bitmap1> {
+        self.resolved_bitmap
+            .as_ref()
+            .or(self.intermediate_bitmap.as_ref())
+    }
+
+    /// Filter the supersampled intermediate bitmap down into the resolved bitmap.
+    /// Both are 2x2 pixels per target pixel apart, so linear filtering averages all four.
+    fn resolve_intermediate(&self) -> Result<()> {
+        let (Some(intermediate_bitmap), Some(resolved_bitmap)) =
+            (&self.intermediate_bitmap, &self.resolved_bitmap)
+        else {
+            return Ok(());
+        };
+        unsafe {
+            let blend = self.d2d_context.GetPrimitiveBlend();
+            self.d2d_context.SetTarget(resolved_bitmap);
+            self.d2d_context.SetDpi(96.0, 96.0);
+            self.d2d_context.BeginDraw();
+            self.d2d_context
+                .SetPrimitiveBlend(D2D1_PRIMITIVE_BLEND_COPY);
+            self.d2d_context.DrawBitmap(
+                intermediate_bitmap,
+                None,
+                1.0,
+                D2D1_INTERPOLATION_MODE_LINEAR,
+                None,
+                None,
+            );
+            let result = self.d2d_context.EndDraw(None, None);
+            self.d2d_context.SetPrimitiveBlend(blend);
+            self.restore_intermediate_target();
+            result.context("Failed to resolve supersampled scene")?;
+        }
+        Ok(())
+    }
+
+    /// Set the opacity the intermediate bitmap is presented with over a black background
+    ///
+    /// Values below 1.0 dim the whole accumulated scene without modifying it. This replaces the
+    /// plain GPU copy in end_draw() with a D2D draw, so keep it at 1.0 when not needed.
+    pub fn set_present_opacity(&mut self, opacity: f32) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        if opacity != self.present_opacity {
+            self.present_opacity = opacity;
+            self.mark_dirty();
+        }
+    }
+
+    /// Set the opacity of every presented frame over the desktop
+    ///
+    /// Below 1.0 the scene is cleared to transparent instead of black and presented through
+    /// the blended path of end_draw(), like `set_present_opacity`.
+    pub fn set_global_alpha(&mut self, alpha: f32) {
+        let alpha = alpha.clamp(0.0, 1.0);
+        if alpha != self.global_alpha {
+            self.global_alpha = alpha;
+            self.mark_dirty();
+        }
+    }
+
+    /// Set how much of the previous presented frame is retained in the next one
+    ///
+    /// Each presented frame becomes `(1.0 - retention) * scene + retention * previous frame`,
+    /// so newly drawn content fades in over a few frames instead of appearing at once.
+    /// Only takes effect in incremental mode; 0.0 turns it off and frees the frame copy.
+    pub fn set_motion_blur(&mut self, retention: f32) -> Result<()> {
+        self.motion_blur = retention.clamp(0.0, 0.95);
+        if self.motion_blur <= 0.0 {
+            // The screen still shows a blended frame, present the unblended scene over it
+            if self.previous_frame_bitmap.take().is_some() {
+                self.mark_dirty();
+            }
+            return Ok(());
+        }
+        if self.previous_frame_bitmap.is_some() || !self.is_incremental() {
+            return Ok(());
+        }
+
+        // Start from the current scene so the first blended frame doesn't fade in from black
+        self.previous_frame_bitmap = Some(self.snapshot_current_target()?);
+        Ok(())
+    }
+
+    /// Copy the content of the current render target into a new bitmap that can be drawn
+    ///
+    /// In incremental mode this is the accumulated scene, otherwise the swap chain buffer.
+    /// The copy happens on the GPU, the returned bitmap is independent of later drawing.
+    pub fn snapshot_current_target(&self) -> Result<ID2D1Bitmap1> {
+        let source = self.scene_bitmap().unwrap_or(&self.d2d_bitmap);
+
+        let bitmap_properties = D2D1_BITMAP_PROPERTIES1 {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.0,
+            dpiY: 96.0,
+            bitmapOptions: D2D1_BITMAP_OPTIONS_NONE,
+            colorContext: ManuallyDrop::new(None),
+        };
+        let snapshot: ID2D1Bitmap1 = unsafe {
+            self.d2d_context
+                .CreateBitmap(
+                    D2D_SIZE_U {
+                        width: self.width,
+                        height: self.height,
+                    },
+                    None,
+                    0,
+                    &bitmap_properties,
+                )
+                .context("Failed to create snapshot bitmap")?
+        };
+
+        unsafe {
+            // Flush pending D2D commands so the snapshot includes everything drawn so far
+            let _ = self.d2d_context.Flush(None, None);
+            snapshot
+                .CopyFromBitmap(None, source, None)
+                .context("Failed to copy render target into snapshot")?;
+        }
+        Ok(snapshot)
+    }
+
+    /// Copy the last finished frame to the CPU as tightly packed BGRA rows (premultiplied alpha)
+    ///
+    /// Must be called outside of begin_draw/end_draw. Meant for headless renderers; on a
+    /// windowed renderer it reads the swap chain buffer, which flip-discard may have invalidated.
+    pub fn read_pixels(&self) -> Result<Vec<u8>> {
+        self.read_bitmap_pixels(&self.d2d_bitmap)
     }
 
-    fn incremental_with_copy(&mut self, copy_existing: bool) -> Result<()> {
-        if self.is_incremental() {
-            return Ok(());
-        }
+    /// Encode the scene as a PNG file at the renderer's size
+    ///
+    /// Saves the intermediate bitmap when rendering incrementally (the scene without overlays),
+    /// the swap chain buffer otherwise. Must be called outside of begin_draw/end_draw.
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        let source = self.scene_bitmap().unwrap_or(&self.d2d_bitmap);
+        let pixels = self.read_bitmap_pixels(source)?;
+
+        let wic_factory = offscreen::create_wic_factory()?;
+        let bitmap = unsafe {
+            wic_factory
+                .CreateBitmapFromMemory(
+                    self.width,
+                    self.height,
+                    &GUID_WICPixelFormat32bppPBGRA,
+                    self.width * 4,
+                    &pixels,
+                )
+                .context("Failed to create WIC bitmap from frame")?
+        };
+        offscreen::write_png(&wic_factory, &bitmap, self.width, self.height, path)?;
+        info!("Saved frame to {}", path.display());
+        Ok(())
+    }
 
-        let intermediate_bitmap_properties = D2D1_BITMAP_PROPERTIES1 {
+    /// Copy `source` to the CPU as tightly packed BGRA rows
+    fn read_bitmap_pixels(&self, source: &ID2D1Bitmap1) -> Result<Vec<u8>> {
+        let bitmap_properties = D2D1_BITMAP_PROPERTIES1 {
             pixelFormat: D2D1_PIXEL_FORMAT {
                 format: DXGI_FORMAT_B8G8R8A8_UNORM,
                 alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
             },
             dpiX: 96.0,
             dpiY: 96.0,
-            bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET,
+            bitmapOptions: D2D1_BITMAP_OPTIONS_CPU_READ | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
             colorContext: ManuallyDrop::new(None),
         };
+        let row_size = self.width as usize * 4;
+        let mut pixels = Vec::with_capacity(row_size * self.height as usize);
 
-        let intermediate_bitmap: ID2D1Bitmap1 = unsafe {
-            self.d2d_context
+        unsafe {
+            let readback: ID2D1Bitmap1 = self
+                .d2d_context
                 .CreateBitmap(
                     D2D_SIZE_U {
                         width: self.width,
@@ -447,73 +1458,173 @@ impl Renderer {
                     },
                     None,
                     0,
-                    &intermediate_bitmap_properties,
+                    &bitmap_properties,
                 )
-                .context("Failed to create intermediate bitmap")?
-        };
-
-        // Copy current swap chain content to intermediate bitmap if requested
-        // Only copy if there's existing content to preserve (e.g., after reverse animation)
-        if copy_existing {
-            unsafe {
-                let src_rect = windows::Win32::Graphics::Direct2D::Common::D2D_RECT_U {
-                    left: 0,
-                    top: 0,
-                    right: self.width,
-                    bottom: self.height,
-                };
-                let dest_point =
-                    windows::Win32::Graphics::Direct2D::Common::D2D_POINT_2U { x: 0, y: 0 };
-
-                intermediate_bitmap
-                    .CopyFromBitmap(Some(&dest_point), &self.d2d_bitmap, Some(&src_rect))
-                    .context("Failed to copy swap chain content to intermediate bitmap")?;
+                .context("Failed to create readback bitmap")?;
+            readback
+                .CopyFromBitmap(None, source, None)
+                .context("Failed to copy frame into readback bitmap")?;
+
+            let mapped = readback
+                .Map(D2D1_MAP_OPTIONS_READ)
+                .context("Failed to map readback bitmap")?;
+            // Rows are padded to `pitch` bytes
+            for y in 0..self.height as usize {
+                let row = std::slice::from_raw_parts(
+                    mapped.bits.add(y * mapped.pitch as usize),
+                    row_size,
+                );
+                pixels.extend_from_slice(row);
             }
+            readback
+                .Unmap()
+                .context("Failed to unmap readback bitmap")?;
         }
+        Ok(pixels)
+    }
+
+    /// Draw the intermediate bitmap onto the swap chain with the configured opacity,
+    /// blending the previous frame over it when motion blur is enabled
+    fn present_intermediate_blended(&self) -> Result<()> {
+        let scene_bitmap = self
+            .scene_bitmap()
+            .context("Intermediate bitmap not initialized")?;
 
-        // Switch render target to intermediate bitmap
         unsafe {
-            self.d2d_context.SetTarget(&intermediate_bitmap);
+            self.target_back_buffer();
+            self.d2d_context.BeginDraw();
+            self.d2d_context.Clear(Some(&self.background_color()));
+            self.d2d_context.DrawBitmap(
+                scene_bitmap,
+                None,
+                self.present_opacity * self.global_alpha,
+                D2D1_INTERPOLATION_MODE_NEAREST_NEIGHBOR,
+                None,
+                None,
+            );
+            if let Some(previous_frame_bitmap) = &self.previous_frame_bitmap {
+                self.d2d_context.DrawBitmap(
+                    previous_frame_bitmap,
+                    None,
+                    self.motion_blur,
+                    D2D1_INTERPOLATION_MODE_NEAREST_NEIGHBOR,
+                    None,
+                    None,
+                );
+            }
+            let result = self.d2d_context.EndDraw(None, None);
+
+            // Always restore the intermediate bitmap as the render target
+            self.restore_intermediate_target();
+            result.context("Failed to present intermediate bitmap")?;
+
+            // Keep the blended frame (without overlays) for the next one
+            if let Some(previous_frame_bitmap) = &self.previous_frame_bitmap {
+                previous_frame_bitmap
+                    .CopyFromBitmap(None, &self.d2d_bitmap, None)
+                    .context("Failed to keep presented frame for motion blur")?;
+            }
         }
+        Ok(())
+    }
 
-        // Extract underlying D3D11 texture for efficient GPU-level copying
-        let intermediate_texture: ID3D11Texture2D = unsafe {
-            intermediate_bitmap
-                .GetSurface()
-                .context("Failed to get surface from intermediate bitmap")?
-                .cast::<ID3D11Texture2D>()
-                .context("Failed to cast surface to ID3D11Texture2D")?
-        };
+    /// Queue operations to draw on top of the next presented frame
+    ///
+    /// Overlay operations are drawn straight onto the swap chain buffer after the scene,
+    /// so they never end up in the accumulated intermediate bitmap. They only last one frame.
+    pub fn queue_overlay(&self, operations: impl IntoIterator<Item = DrawOperation>) {
+        let mut overlay = self.overlay_operations.borrow_mut();
+        let queued = overlay.len();
+        overlay.extend(operations);
+        if overlay.len() > queued {
+            self.mark_dirty();
+        }
+    }
 
-        self.intermediate_bitmap = Some(intermediate_bitmap);
-        self.intermediate_texture = Some(intermediate_texture);
+    /// Set text that is drawn on top of every presented frame until changed
+    ///
+    /// Like the queued overlay it never ends up in the intermediate bitmap, so it is unaffected
+    /// by erasing and presentation opacity.
+    pub fn set_text_overlay(&mut self, overlay: Option<TextOverlay>) {
+        if overlay != self.text_overlay {
+            self.text_overlay = overlay;
+            self.mark_dirty();
+        }
+    }
 
-        Ok(())
+    /// Set a recorded command list (see `create_command_list`) that is drawn on top of every
+    /// presented frame until changed, below the queued overlay and the text overlay
+    pub fn set_static_overlay(&mut self, overlay: Option<ID2D1CommandList>) {
+        if overlay.is_some() || self.static_overlay.is_some() {
+            self.static_overlay = overlay;
+            self.mark_dirty();
+        }
     }
 
-    pub fn non_incremental(&mut self) {
-        if !self.is_incremental() {
-            return;
+    /// Draw the static overlay, the queued overlay operations and the text overlay onto the
+    /// swap chain buffer
+    fn draw_overlay(&self) -> Result<()> {
+        let overlay = self.overlay_operations.take();
+        if overlay.is_empty() && self.text_overlay.is_none() && self.static_overlay.is_none() {
+            return Ok(());
         }
 
-        // Clear intermediate bitmap and switch back to swap chain bitmap
         unsafe {
-            self.d2d_context.SetTarget(&self.d2d_bitmap);
+            self.target_back_buffer();
+            self.d2d_context.BeginDraw();
+            self.d2d_context
+                .SetPrimitiveBlend(D2D1_PRIMITIVE_BLEND_SOURCE_OVER);
+            let result = self
+                .static_overlay
+                .as_ref()
+                .map_or(Ok(()), |command_list| self.draw_command_list(command_list))
+                .and_then(|_| self.draw_batch(&overlay))
+                .and_then(|_| match &self.text_overlay {
+                    Some(text) => self.draw_text(&text.text, &text.format, &text.rect, &text.color),
+                    None => Ok(()),
+                });
+            let end_result = self.d2d_context.EndDraw(None, None);
+
+            // Restore the intermediate bitmap as the render target
+            self.restore_intermediate_target();
+            result?;
+            end_result.context("Failed to draw overlay")?;
         }
-        self.intermediate_bitmap = None;
-        self.intermediate_texture = None;
+        Ok(())
     }
 
     /// End a rendering frame and present to screen
     pub fn end_draw(&self) -> Result<()> {
         // Finish drawing to intermediate bitmap
-        unsafe {
-            self.d2d_context
-                .EndDraw(None, None)
-                .context("Direct2D EndDraw failed")?;
+        if let Err(error) = unsafe { self.d2d_context.EndDraw(None, None) } {
+            self.check_device_lost(error.code())?;
+            return Err(error).context("Direct2D EndDraw failed");
         }
 
-        if self.is_incremental() {
+        // Nothing changed on screen, don't make DWM compose an identical frame.
+        // Motion blur keeps converging towards the scene, so it presents every frame.
+        if self.dirty.get() == DirtyRegion::Clean && self.previous_frame_bitmap.is_none() {
+            return Ok(());
+        }
+
+        // Overlays are drawn over the frame and gone from the next one, the blended path redraws
+        // everything, both change more than what was drawn
+        let has_overlay = !self.overlay_operations.borrow().is_empty()
+            || self.text_overlay.is_some()
+            || self.static_overlay.is_some();
+        let had_overlay = self.overlay_on_screen.replace(has_overlay);
+        let blended = self.is_incremental()
+            && (self.present_opacity < 1.0
+                || self.global_alpha < 1.0
+                || self.previous_frame_bitmap.is_some());
+        if has_overlay || had_overlay || blended {
+            self.mark_dirty();
+        }
+        self.resolve_intermediate()?;
+
+        if blended {
+            self.present_intermediate_blended()?;
+        } else if self.is_incremental() {
             // Use Direct3D GPU copy instead of D2D DrawBitmap for 2-5x better performance
             // This bypasses the entire D2D rendering pipeline (no shader, no command buffer overhead)
             unsafe {
@@ -521,45 +1632,99 @@ impl Renderer {
                 let _ = self.d2d_context.Flush(None, None);
 
                 // Direct GPU memory copy (pure memcpy on GPU, bypasses D2D entirely)
-                self.d3d_context.CopyResource(
-                    &self.swap_chain_texture,
-                    self.intermediate_texture.as_ref().unwrap(),
-                );
+                let scene_texture = self
+                    .resolved_texture
+                    .as_ref()
+                    .or(self.intermediate_texture.as_ref());
+                self.d3d_context
+                    .CopyResource(&self.swap_chain_texture, scene_texture.unwrap());
             }
         }
 
+        self.draw_overlay()?;
+
+        // Headless renderers keep the frame in their target bitmap for read_pixels()
+        let Some(presentation) = &self.presentation else {
+            self.dirty.set(DirtyRegion::Clean);
+            return Ok(());
+        };
+
         unsafe {
             // Present to screen with configured vsync setting
-            let present_hr = self.swap_chain.Present(self.sync_interval, DXGI_PRESENT(0));
+            let present_flags = if presentation.allow_tearing && self.sync_interval == 0 {
+                DXGI_PRESENT_ALLOW_TEARING
+            } else {
+                DXGI_PRESENT(0)
+            };
+            // The whole back buffer is always rewritten, the dirty rectangle is only a hint
+            // for the compositor
+            let present_hr = match self.dirty_rect() {
+                Some(mut dirty_rect) => presentation.swap_chain.Present1(
+                    self.sync_interval,
+                    present_flags,
+                    &DXGI_PRESENT_PARAMETERS {
+                        DirtyRectsCount: 1,
+                        pDirtyRects: &mut dirty_rect,
+                        ..Default::default()
+                    },
+                ),
+                None => presentation
+                    .swap_chain
+                    .Present(self.sync_interval, present_flags),
+            };
 
-            // Check for device loss errors
+            // Device loss is recoverable by recreating the renderer, anything else is not
             if present_hr.is_err() {
-                if present_hr == DXGI_ERROR_DEVICE_REMOVED {
-                    // Get the reason for device removal
-                    let device_removed_reason = self.d3d_device.GetDeviceRemovedReason();
-                    anyhow::bail!(
-                        "GPU device removed during Present (DXGI_ERROR_DEVICE_REMOVED). \
-                         Reason: {:?}. This typically indicates a GPU driver crash or hardware issue.",
-                        device_removed_reason
-                    );
-                } else if present_hr == DXGI_ERROR_DEVICE_RESET {
-                    anyhow::bail!(
-                        "GPU device reset during Present (DXGI_ERROR_DEVICE_RESET). \
-                         Device needs to be recreated."
-                    );
-                } else {
-                    present_hr.ok().context("Present failed")?;
-                }
+                self.check_device_lost(present_hr)?;
+                present_hr.ok().context("Present failed")?;
             }
+            presentation.frame_slot.set(false);
 
-            self.composition_device
+            presentation
+                .composition_device
                 .Commit()
                 .context("DirectComposition Commit failed")?;
         }
 
+        // Cleared last, drawing the overlay marks the renderer dirty as well
+        self.dirty.set(DirtyRegion::Clean);
         Ok(())
     }
 
+    /// Remember that the next end_draw() has to present the whole frame
+    fn mark_dirty(&self) {
+        self.dirty.set(DirtyRegion::Full);
+    }
+
+    /// Remember that the next end_draw() has to present at least `rect`
+    fn mark_dirty_rect(&self, rect: D2D_RECT_F) {
+        self.dirty.set(match self.dirty.get() {
+            DirtyRegion::Clean => DirtyRegion::Rect(rect),
+            DirtyRegion::Rect(dirty) => DirtyRegion::Rect(D2D_RECT_F {
+                left: dirty.left.min(rect.left),
+                top: dirty.top.min(rect.top),
+                right: dirty.right.max(rect.right),
+                bottom: dirty.bottom.max(rect.bottom),
+            }),
+            DirtyRegion::Full => DirtyRegion::Full,
+        });
+    }
+
+    /// Pixels to present as dirty, None to present the whole frame
+    fn dirty_rect(&self) -> Option<RECT> {
+        let DirtyRegion::Rect(dirty) = self.dirty.get() else {
+            return None;
+        };
+        // Rounded outwards plus a pixel, antialiased edges reach past the exact bounds
+        let rect = RECT {
+            left: (dirty.left.floor() as i32 - 1).max(0),
+            top: (dirty.top.floor() as i32 - 1).max(0),
+            right: (dirty.right.ceil() as i32 + 1).min(self.width as i32),
+            bottom: (dirty.bottom.ceil() as i32 + 1).min(self.height as i32),
+        };
+        (rect.left < rect.right && rect.top < rect.bottom).then_some(rect)
+    }
+
     /// Create a command list from operations (for caching/replay)
     /// This must be called OUTSIDE of a BeginDraw/EndDraw pair
     pub fn create_command_list(&self, operations: &[DrawOperation]) -> Result<ID2D1CommandList> {
@@ -590,6 +1755,7 @@ impl Renderer {
 
     /// Draw a command list
     pub fn draw_command_list(&self, command_list: &ID2D1CommandList) -> Result<()> {
+        self.mark_dirty();
         unsafe {
             self.d2d_context.DrawImage(
                 command_list,
@@ -605,7 +1771,29 @@ impl Renderer {
     /// Set blend mode to MIN for pixel-perfect erasure
     /// MIN mode: O = Min(S + 1-SA, D), so drawing black (0,0,0) always results in black
     /// This handles partial pixel coverage correctly unlike COPY mode
+    ///
+    /// With a background loaded, batches are drawn with the background brush instead so
+    /// erased roads reveal the image again (aliased drawing has no partial coverage to handle).
+    ///
+    /// A transparent background can't be restored with MIN, which keeps the alpha of the erased
+    /// pixels, so batches copy transparent black over them instead.
     pub fn set_min_blend(&self) {
+        unsafe {
+            self.d2d_context
+                .SetAntialiasMode(D2D1_ANTIALIAS_MODE_ALIASED);
+        }
+        if self.background_brush.is_some() {
+            self.erasing.set(true);
+            return;
+        }
+        if self.global_alpha < 1.0 {
+            self.erasing.set(true);
+            unsafe {
+                self.d2d_context
+                    .SetPrimitiveBlend(D2D1_PRIMITIVE_BLEND_COPY);
+            }
+            return;
+        }
         unsafe {
             self.d2d_context.SetPrimitiveBlend(D2D1_PRIMITIVE_BLEND_MIN);
         }
@@ -613,12 +1801,44 @@ impl Renderer {
 
     /// Restore normal blend mode
     pub fn set_normal_blend(&self) {
+        self.erasing.set(false);
         unsafe {
+            self.d2d_context.SetAntialiasMode(self.antialias_mode());
             self.d2d_context
                 .SetPrimitiveBlend(D2D1_PRIMITIVE_BLEND_SOURCE_OVER);
         }
     }
 
+    /// Draw with antialiased edges from the next frame on, off draws pixel-perfect aliased lines
+    ///
+    /// Erasure (`set_min_blend`) always draws aliased, so antialiased edges may leave a faint
+    /// fringe when erased.
+    pub fn set_antialias(&mut self, enabled: bool) {
+        self.antialias = enabled;
+    }
+
+    fn antialias_mode(&self) -> D2D1_ANTIALIAS_MODE {
+        if self.antialias {
+            D2D1_ANTIALIAS_MODE_PER_PRIMITIVE
+        } else {
+            D2D1_ANTIALIAS_MODE_ALIASED
+        }
+    }
+
+    /// Stroke polylines with round joins instead of mitered ones, caps stay flat
+    pub fn set_round_joins(&mut self, enabled: bool) {
+        self.round_joins = enabled;
+    }
+
+    /// Stroke style used for lines, polylines and rectangle outlines
+    fn stroke_style(&self) -> &ID2D1StrokeStyle {
+        if self.round_joins {
+            &self.round_join_stroke_style
+        } else {
+            &self.flat_cap_stroke_style
+        }
+    }
+
     /// Draw a line between two points
     pub fn draw_line(
         &self,
@@ -627,15 +1847,39 @@ impl Renderer {
         color: &D2D1_COLOR_F,
         thickness: f32,
     ) -> Result<()> {
+        self.mark_dirty();
+        let brush = self.get_solid_brush(color)?;
+        unsafe {
+            self.d2d_context
+                .DrawLine(start, end, &brush, thickness, self.stroke_style());
+        }
+        Ok(())
+    }
+
+    /// Draw a circular arc, see `DrawOperation::Arc` for the angle convention
+    ///
+    /// Stroked with the same style as lines, so arcs end in the same flat caps.
+    pub fn draw_arc(
+        &self,
+        center: Vector2,
+        radius: f32,
+        start_angle: f32,
+        sweep_angle: f32,
+        color: &D2D1_COLOR_F,
+        thickness: f32,
+    ) -> Result<()> {
+        self.mark_dirty();
         let brush = self.get_solid_brush(color)?;
+        let path = arc_geometry(&self.d2d_factory, center, radius, start_angle, sweep_angle)?;
         unsafe {
             self.d2d_context
-                .DrawLine(start, end, &brush, thickness, &self.flat_cap_stroke_style);
+                .DrawGeometry(&path, &brush, thickness, self.stroke_style());
         }
         Ok(())
     }
 
     pub fn draw_rect(&self, rect: &D2D_RECT_F, color: &D2D1_COLOR_F, thickness: f32) -> Result<()> {
+        self.mark_dirty();
         let brush = self.get_solid_brush(color)?;
         unsafe {
             self.d2d_context
@@ -645,6 +1889,7 @@ impl Renderer {
     }
 
     pub fn draw_filled_rect(&self, rect: &D2D_RECT_F, color: &D2D1_COLOR_F) -> Result<()> {
+        self.mark_dirty();
         let brush = self.get_solid_brush(color)?;
         unsafe {
             self.d2d_context.FillRectangle(rect, &brush);
@@ -652,12 +1897,33 @@ impl Renderer {
         Ok(())
     }
 
+    pub fn draw_filled_rounded_rect(
+        &self,
+        rect: &D2D_RECT_F,
+        radius_x: f32,
+        radius_y: f32,
+        color: &D2D1_COLOR_F,
+    ) -> Result<()> {
+        self.mark_dirty();
+        let brush = self.get_solid_brush(color)?;
+        let rounded_rect = D2D1_ROUNDED_RECT {
+            rect: *rect,
+            radiusX: radius_x,
+            radiusY: radius_y,
+        };
+        unsafe {
+            self.d2d_context.FillRoundedRectangle(&rounded_rect, &brush);
+        }
+        Ok(())
+    }
+
     pub fn draw_polyline(
         &self,
         points: &[Vector2],
         color: &D2D1_COLOR_F,
         thickness: f32,
     ) -> Result<()> {
+        self.mark_dirty();
         let brush = self.get_solid_brush(color)?;
 
         // Create path geometry for the polyline
@@ -684,12 +1950,8 @@ impl Renderer {
         }
 
         unsafe {
-            self.d2d_context.DrawGeometry(
-                &path_geometry,
-                &brush,
-                thickness,
-                &self.flat_cap_stroke_style,
-            );
+            self.d2d_context
+                .DrawGeometry(&path_geometry, &brush, thickness, self.stroke_style());
         }
         Ok(())
     }
@@ -702,6 +1964,9 @@ impl Renderer {
         if operations.is_empty() {
             return Ok(());
         }
+        for op in operations {
+            self.mark_dirty_rect(op.bounds());
+        }
 
         // Group operations by color and type to minimize brush switches
         use std::collections::HashMap;
@@ -713,9 +1978,15 @@ impl Renderer {
         }
 
         let mut grouped: HashMap<DrawKey, Vec<&DrawOperation>> = HashMap::new();
+        // Gradients have no single color to group by, each brings its own brush
+        let mut gradients = Vec::new();
 
         for op in operations {
             let (color_key, is_fill, thickness) = match op {
+                DrawOperation::GradientRect { .. } => {
+                    gradients.push(op);
+                    continue;
+                }
                 DrawOperation::Line {
                     color, thickness, ..
                 } => (Self::color_to_key(color), false, *thickness),
@@ -723,9 +1994,30 @@ impl Renderer {
                     color, thickness, ..
                 } => (Self::color_to_key(color), false, *thickness),
                 DrawOperation::FilledRect { color, .. } => (Self::color_to_key(color), true, 0.0),
+                DrawOperation::FilledRoundedRect { color, .. } => {
+                    (Self::color_to_key(color), true, 0.0)
+                }
                 DrawOperation::Polyline {
                     color, thickness, ..
+                }
+                | DrawOperation::BezierPath {
+                    color, thickness, ..
+                }
+                | DrawOperation::Arc {
+                    color, thickness, ..
                 } => (Self::color_to_key(color), false, *thickness),
+                DrawOperation::Ellipse {
+                    color,
+                    filled,
+                    thickness,
+                    ..
+                } => (
+                    Self::color_to_key(color),
+                    *filled,
+                    if *filled { 0.0 } else { *thickness },
+                ),
+                // Glyphs are filled with the brush
+                DrawOperation::Text { color, .. } => (Self::color_to_key(color), true, 0.0),
             };
 
             let key = DrawKey {
@@ -739,14 +2031,63 @@ impl Renderer {
         // Process each color/type group - use direct drawing (no geometry creation overhead)
         for (key, ops) in grouped {
             let color = Self::key_to_color(key.color_key);
-            let brush = self.get_solid_brush(&color)?;
+            let brush: ID2D1Brush = match &self.background_brush {
+                Some(background_brush) if self.erasing.get() => background_brush.cast()?,
+                _ if self.erasing.get() => {
+                    self.get_solid_brush(&self.background_color())?.cast()?
+                }
+                _ => self.get_solid_brush(&color)?.cast()?,
+            };
 
             unsafe {
                 if key.is_fill {
                     // Draw filled rectangles directly
                     for op in ops {
-                        if let DrawOperation::FilledRect { rect, .. } = op {
-                            self.d2d_context.FillRectangle(rect, &brush);
+                        match op {
+                            DrawOperation::FilledRect { rect, .. } => {
+                                self.d2d_context.FillRectangle(rect, &brush);
+                            }
+                            // Rounded rectangles can't share the plain rectangle path
+                            DrawOperation::FilledRoundedRect {
+                                rect,
+                                radius_x,
+                                radius_y,
+                                ..
+                            } => {
+                                let rounded_rect = D2D1_ROUNDED_RECT {
+                                    rect: *rect,
+                                    radiusX: *radius_x,
+                                    radiusY: *radius_y,
+                                };
+                                self.d2d_context.FillRoundedRectangle(&rounded_rect, &brush);
+                            }
+                            DrawOperation::Ellipse {
+                                center,
+                                radius_x,
+                                radius_y,
+                                ..
+                            } => {
+                                let ellipse = D2D1_ELLIPSE {
+                                    point: *center,
+                                    radiusX: *radius_x,
+                                    radiusY: *radius_y,
+                                };
+                                self.d2d_context.FillEllipse(&ellipse, &brush);
+                            }
+                            DrawOperation::Text {
+                                text, format, rect, ..
+                            } => {
+                                let text_wide: Vec<u16> = text.encode_utf16().collect();
+                                self.d2d_context.DrawText(
+                                    &text_wide,
+                                    format,
+                                    rect,
+                                    &brush,
+                                    D2D1_DRAW_TEXT_OPTIONS_NONE,
+                                    DWRITE_MEASURING_MODE_NATURAL,
+                                );
+                            }
+                            _ => {}
                         }
                     }
                 } else {
@@ -760,7 +2101,7 @@ impl Renderer {
                                     *end,
                                     &brush,
                                     thickness,
-                                    &self.flat_cap_stroke_style,
+                                    self.stroke_style(),
                                 );
                             }
                             DrawOperation::Rect { rect, .. } => {
@@ -768,7 +2109,55 @@ impl Renderer {
                                     rect,
                                     &brush,
                                     thickness,
-                                    &self.flat_cap_stroke_style,
+                                    self.stroke_style(),
+                                );
+                            }
+                            DrawOperation::Ellipse {
+                                center,
+                                radius_x,
+                                radius_y,
+                                ..
+                            } => {
+                                let ellipse = D2D1_ELLIPSE {
+                                    point: *center,
+                                    radiusX: *radius_x,
+                                    radiusY: *radius_y,
+                                };
+                                self.d2d_context.DrawEllipse(
+                                    &ellipse,
+                                    &brush,
+                                    thickness,
+                                    self.stroke_style(),
+                                );
+                            }
+                            DrawOperation::BezierPath { points, .. } if points.len() >= 2 => {
+                                let path = bezier_path_geometry(&self.d2d_factory, points)?;
+                                self.d2d_context.DrawGeometry(
+                                    &path,
+                                    &brush,
+                                    thickness,
+                                    self.stroke_style(),
+                                );
+                            }
+                            DrawOperation::Arc {
+                                center,
+                                radius,
+                                start_angle,
+                                sweep_angle,
+                                ..
+                            } => {
+                                let path = arc_geometry(
+                                    &self.d2d_factory,
+                                    *center,
+                                    *radius,
+                                    *start_angle,
+                                    *sweep_angle,
+                                )?;
+                                self.d2d_context.DrawGeometry(
+                                    &path,
+                                    &brush,
+                                    thickness,
+                                    self.stroke_style(),
                                 );
                             }
                             DrawOperation::Polyline { points, .. } => {
@@ -785,7 +2174,7 @@ impl Renderer {
                                         &path,
                                         &brush,
                                         thickness,
-                                        &self.flat_cap_stroke_style,
+                                        self.stroke_style(),
                                     );
                                 }
                             }
@@ -796,6 +2185,21 @@ impl Renderer {
             }
         }
 
+        for op in gradients {
+            if let DrawOperation::GradientRect {
+                rect,
+                stops,
+                start,
+                end,
+            } = op
+            {
+                let brush = self.get_linear_gradient_brush(stops, *start, *end)?;
+                unsafe {
+                    self.d2d_context.FillRectangle(rect, &brush);
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -842,6 +2246,62 @@ impl Renderer {
         Ok(brush)
     }
 
+    /// Get a linear gradient brush from `start` to `end` with the given (position, color) stops
+    ///
+    /// Brushes are cached on their stops only, a cached brush is moved to `start`/`end`.
+    pub fn get_linear_gradient_brush(
+        &self,
+        stops: &[(f32, D2D1_COLOR_F)],
+        start: Vector2,
+        end: Vector2,
+    ) -> Result<ID2D1LinearGradientBrush> {
+        let key: Vec<(u32, u32)> = stops
+            .iter()
+            .map(|(position, color)| (position.to_bits(), Self::color_to_key(color)))
+            .collect();
+
+        if let Some(brush) = self.gradient_brush_cache.borrow().get(&key) {
+            unsafe {
+                brush.SetStartPoint(start);
+                brush.SetEndPoint(end);
+            }
+            return Ok(brush.clone());
+        }
+
+        let gradient_stops: Vec<D2D1_GRADIENT_STOP> = stops
+            .iter()
+            .map(|(position, color)| D2D1_GRADIENT_STOP {
+                position: *position,
+                color: *color,
+            })
+            .collect();
+        let brush = unsafe {
+            // The plain render target version, the device context one adds color space options
+            let stop_collection = ID2D1RenderTarget::CreateGradientStopCollection(
+                &self.d2d_context,
+                &gradient_stops,
+                D2D1_GAMMA_2_2,
+                D2D1_EXTEND_MODE_CLAMP,
+            )
+            .context("Failed to create gradient stop collection")?;
+            self.d2d_context
+                .CreateLinearGradientBrush(
+                    &D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
+                        startPoint: start,
+                        endPoint: end,
+                    },
+                    None,
+                    &stop_collection,
+                )
+                .context("Failed to create linear gradient brush")?
+        };
+        self.gradient_brush_cache
+            .borrow_mut()
+            .insert(key, brush.clone());
+
+        Ok(brush)
+    }
+
     /// Create a text format for rendering text
     pub fn create_text_format(
         &self,
@@ -874,6 +2334,56 @@ impl Renderer {
         }
     }
 
+    /// Size (width, height) the text takes up when laid out with `format`, wrapping lines
+    /// longer than `max_width`
+    pub fn measure_text(
+        &self,
+        text: &str,
+        format: &IDWriteTextFormat,
+        max_width: f32,
+    ) -> Result<(f32, f32)> {
+        if text.is_empty() {
+            return Ok((0.0, 0.0));
+        }
+
+        let text_wide: Vec<u16> = text.encode_utf16().collect();
+        unsafe {
+            let layout = self
+                .dwrite_factory
+                .CreateTextLayout(&text_wide, format, max_width.max(0.0), f32::MAX)
+                .context("Failed to create text layout")?;
+            let mut metrics = DWRITE_TEXT_METRICS::default();
+            layout
+                .GetMetrics(&mut metrics)
+                .context("Failed to measure text")?;
+            Ok((metrics.widthIncludingTrailingWhitespace, metrics.height))
+        }
+    }
+
+    /// Draw text inside the given layout rectangle
+    pub fn draw_text(
+        &self,
+        text: &str,
+        format: &IDWriteTextFormat,
+        rect: &D2D_RECT_F,
+        color: &D2D1_COLOR_F,
+    ) -> Result<()> {
+        self.mark_dirty();
+        let brush = self.get_solid_brush(color)?;
+        let text_wide: Vec<u16> = text.encode_utf16().collect();
+        unsafe {
+            self.d2d_context.DrawText(
+                &text_wide,
+                format,
+                rect,
+                &brush,
+                D2D1_DRAW_TEXT_OPTIONS_NONE,
+                DWRITE_MEASURING_MODE_NATURAL,
+            );
+        }
+        Ok(())
+    }
+
     /// Phase 3: Create a command list from operations for efficient replay
     pub fn create_command_list_from_operations(
         &self,
@@ -915,3 +2425,87 @@ impl Renderer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 16;
+    const HEIGHT: u32 = 16;
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    /// BGRA bytes of the pixel at (x, y) of a `read_pixels` result
+    fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * WIDTH + x) * 4) as usize;
+        pixels[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn clear_fills_every_pixel() -> Result<()> {
+        let renderer = Renderer::new_headless(WIDTH, HEIGHT)?;
+        renderer.begin_draw();
+        renderer.clear(D2D1_COLOR_F {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        });
+        renderer.end_draw()?;
+
+        let pixels = renderer.read_pixels()?;
+        assert_eq!(pixels.len(), (WIDTH * HEIGHT * 4) as usize);
+        assert!(
+            pixels
+                .chunks_exact(4)
+                .all(|pixel| pixel == [0, 0, 255, 255])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filled_rect_covers_only_its_pixels() -> Result<()> {
+        let renderer = Renderer::new_headless(WIDTH, HEIGHT)?;
+        renderer.begin_draw();
+        renderer.clear(D2D1_COLOR_F::black());
+        let rect = D2D_RECT_F {
+            left: 4.0,
+            top: 4.0,
+            right: 8.0,
+            bottom: 8.0,
+        };
+        let white = D2D1_COLOR_F {
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+            a: 1.0,
+        };
+        renderer.draw_batch(&[DrawOperation::filled_rect(rect, white)])?;
+        renderer.end_draw()?;
+
+        let pixels = renderer.read_pixels()?;
+        assert_eq!(pixel(&pixels, 4, 4), WHITE);
+        assert_eq!(pixel(&pixels, 7, 7), WHITE);
+        assert_eq!(pixel(&pixels, 3, 4), BLACK);
+        assert_eq!(pixel(&pixels, 8, 8), BLACK);
+        Ok(())
+    }
+
+    #[test]
+    fn intermediate_bitmap_starts_as_background() -> Result<()> {
+        let mut renderer = Renderer::new_headless(WIDTH, HEIGHT)?;
+        renderer.incremental_no_copy()?;
+        let intermediate = renderer.scene_bitmap().expect("incremental renderer");
+        let pixels = renderer.read_bitmap_pixels(intermediate)?;
+        assert!(pixels.chunks_exact(4).all(|pixel| pixel == BLACK));
+
+        // Transparent black when the desktop shows through
+        let mut renderer = Renderer::new_headless(WIDTH, HEIGHT)?;
+        renderer.set_global_alpha(0.5);
+        renderer.incremental_no_copy()?;
+        let intermediate = renderer.scene_bitmap().expect("incremental renderer");
+        let pixels = renderer.read_bitmap_pixels(intermediate)?;
+        assert!(pixels.chunks_exact(4).all(|pixel| pixel == [0, 0, 0, 0]));
+        Ok(())
+    }
+}
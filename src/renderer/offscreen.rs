@@ -0,0 +1,380 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use windows::{
+    Win32::{
+        Foundation::{GENERIC_WRITE, HGLOBAL, RPC_E_CHANGED_MODE},
+        Graphics::{
+            Direct2D::{
+                Common::{
+                    D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_COLOR_F, D2D1_GRADIENT_STOP,
+                    D2D1_PIXEL_FORMAT,
+                },
+                D2D1_ANTIALIAS_MODE_ALIASED, D2D1_DRAW_TEXT_OPTIONS_NONE, D2D1_ELLIPSE,
+                D2D1_EXTEND_MODE_CLAMP, D2D1_FACTORY_TYPE_SINGLE_THREADED,
+                D2D1_FEATURE_LEVEL_DEFAULT, D2D1_GAMMA_2_2, D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES,
+                D2D1_RENDER_TARGET_PROPERTIES, D2D1_RENDER_TARGET_TYPE_DEFAULT,
+                D2D1_RENDER_TARGET_USAGE_NONE, D2D1_ROUNDED_RECT, D2D1CreateFactory, ID2D1Factory1,
+                ID2D1RenderTarget, ID2D1SolidColorBrush,
+            },
+            DirectWrite::DWRITE_MEASURING_MODE_NATURAL,
+            Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM,
+            Imaging::{
+                CLSID_WICImagingFactory, GUID_ContainerFormatPng, GUID_WICPixelFormat32bppPBGRA,
+                IWICBitmap, IWICImagingFactory, WICBitmapCacheOnDemand, WICBitmapEncoderNoCache,
+            },
+        },
+        System::Com::{
+            CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx, IStream,
+            STATFLAG_NONAME, STATSTG, STREAM_SEEK_SET, StructuredStorage::CreateStreamOnHGlobal,
+        },
+    },
+    core::HSTRING,
+};
+
+use crate::ext::color_ext::D2DColorExt;
+use crate::renderer::{arc_geometry, bezier_path_geometry, draw_operation::DrawOperation};
+
+/// CPU renderer drawing into a WIC bitmap, for images generated without a window
+///
+/// Only supports the subset needed to replay draw operations; the live wallpaper keeps using
+/// the GPU `Renderer`.
+pub struct OffscreenRenderer {
+    wic_factory: IWICImagingFactory,
+    bitmap: IWICBitmap,
+    target: ID2D1RenderTarget,
+    width: u32,
+    height: u32,
+}
+
+impl OffscreenRenderer {
+    pub fn new(width: u32, height: u32) -> Result<Self> {
+        // WIC is COM based
+        let wic_factory = create_wic_factory()?;
+        unsafe {
+            let bitmap = wic_factory
+                .CreateBitmap(
+                    width,
+                    height,
+                    &GUID_WICPixelFormat32bppPBGRA,
+                    WICBitmapCacheOnDemand,
+                )
+                .context("Failed to create WIC bitmap")?;
+
+            let d2d_factory: ID2D1Factory1 =
+                D2D1CreateFactory(D2D1_FACTORY_TYPE_SINGLE_THREADED, None)
+                    .context("Failed to create Direct2D factory")?;
+            let target_properties = D2D1_RENDER_TARGET_PROPERTIES {
+                r#type: D2D1_RENDER_TARGET_TYPE_DEFAULT,
+                pixelFormat: D2D1_PIXEL_FORMAT {
+                    format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                    alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+                },
+                dpiX: 96.0,
+                dpiY: 96.0,
+                usage: D2D1_RENDER_TARGET_USAGE_NONE,
+                minLevel: D2D1_FEATURE_LEVEL_DEFAULT,
+            };
+            let target = d2d_factory
+                .CreateWicBitmapRenderTarget(&bitmap, &target_properties)
+                .context("Failed to create WIC bitmap render target")?;
+
+            Ok(Self {
+                wic_factory,
+                bitmap,
+                target,
+                width,
+                height,
+            })
+        }
+    }
+
+    /// Clear the bitmap to black and draw the operations in order
+    pub fn draw(&self, operations: &[DrawOperation]) -> Result<()> {
+        unsafe {
+            self.target.BeginDraw();
+            self.target.SetAntialiasMode(D2D1_ANTIALIAS_MODE_ALIASED);
+            self.target.Clear(Some(&D2D1_COLOR_F::black()));
+        }
+
+        for operation in operations {
+            self.draw_operation(operation)?;
+        }
+
+        unsafe {
+            self.target
+                .EndDraw(None, None)
+                .context("Failed to draw offscreen operations")?;
+        }
+        Ok(())
+    }
+
+    fn draw_operation(&self, operation: &DrawOperation) -> Result<()> {
+        unsafe {
+            match operation {
+                DrawOperation::Line {
+                    start,
+                    end,
+                    color,
+                    thickness,
+                } => {
+                    let brush = self.brush(color)?;
+                    self.target.DrawLine(*start, *end, &brush, *thickness, None);
+                }
+                DrawOperation::Rect {
+                    rect,
+                    color,
+                    thickness,
+                } => {
+                    let brush = self.brush(color)?;
+                    self.target.DrawRectangle(rect, &brush, *thickness, None);
+                }
+                DrawOperation::FilledRect { rect, color } => {
+                    let brush = self.brush(color)?;
+                    self.target.FillRectangle(rect, &brush);
+                }
+                DrawOperation::FilledRoundedRect {
+                    rect,
+                    radius_x,
+                    radius_y,
+                    color,
+                } => {
+                    let brush = self.brush(color)?;
+                    let rounded_rect = D2D1_ROUNDED_RECT {
+                        rect: *rect,
+                        radiusX: *radius_x,
+                        radiusY: *radius_y,
+                    };
+                    self.target.FillRoundedRectangle(&rounded_rect, &brush);
+                }
+                DrawOperation::Polyline {
+                    points,
+                    color,
+                    thickness,
+                } => {
+                    let brush = self.brush(color)?;
+                    for segment in points.windows(2) {
+                        self.target
+                            .DrawLine(segment[0], segment[1], &brush, *thickness, None);
+                    }
+                }
+                DrawOperation::BezierPath {
+                    points,
+                    color,
+                    thickness,
+                } => {
+                    if points.len() >= 2 {
+                        let brush = self.brush(color)?;
+                        let path = bezier_path_geometry(&self.target.GetFactory()?, points)?;
+                        self.target.DrawGeometry(&path, &brush, *thickness, None);
+                    }
+                }
+                DrawOperation::Arc {
+                    center,
+                    radius,
+                    start_angle,
+                    sweep_angle,
+                    color,
+                    thickness,
+                } => {
+                    let brush = self.brush(color)?;
+                    let path = arc_geometry(
+                        &self.target.GetFactory()?,
+                        *center,
+                        *radius,
+                        *start_angle,
+                        *sweep_angle,
+                    )?;
+                    self.target.DrawGeometry(&path, &brush, *thickness, None);
+                }
+                DrawOperation::GradientRect {
+                    rect,
+                    stops,
+                    start,
+                    end,
+                } => {
+                    let gradient_stops: Vec<D2D1_GRADIENT_STOP> = stops
+                        .iter()
+                        .map(|(position, color)| D2D1_GRADIENT_STOP {
+                            position: *position,
+                            color: *color,
+                        })
+                        .collect();
+                    let stop_collection = self
+                        .target
+                        .CreateGradientStopCollection(
+                            &gradient_stops,
+                            D2D1_GAMMA_2_2,
+                            D2D1_EXTEND_MODE_CLAMP,
+                        )
+                        .context("Failed to create gradient stop collection")?;
+                    let brush = self
+                        .target
+                        .CreateLinearGradientBrush(
+                            &D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
+                                startPoint: *start,
+                                endPoint: *end,
+                            },
+                            None,
+                            &stop_collection,
+                        )
+                        .context("Failed to create linear gradient brush")?;
+                    self.target.FillRectangle(rect, &brush);
+                }
+                DrawOperation::Ellipse {
+                    center,
+                    radius_x,
+                    radius_y,
+                    color,
+                    filled,
+                    thickness,
+                } => {
+                    let brush = self.brush(color)?;
+                    let ellipse = D2D1_ELLIPSE {
+                        point: *center,
+                        radiusX: *radius_x,
+                        radiusY: *radius_y,
+                    };
+                    if *filled {
+                        self.target.FillEllipse(&ellipse, &brush);
+                    } else {
+                        self.target.DrawEllipse(&ellipse, &brush, *thickness, None);
+                    }
+                }
+                DrawOperation::Text {
+                    text,
+                    format,
+                    rect,
+                    color,
+                } => {
+                    let brush = self.brush(color)?;
+                    let text_wide: Vec<u16> = text.encode_utf16().collect();
+                    self.target.DrawText(
+                        &text_wide,
+                        format,
+                        rect,
+                        &brush,
+                        D2D1_DRAW_TEXT_OPTIONS_NONE,
+                        DWRITE_MEASURING_MODE_NATURAL,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn brush(&self, color: &D2D1_COLOR_F) -> Result<ID2D1SolidColorBrush> {
+        unsafe {
+            self.target
+                .CreateSolidColorBrush(color, None)
+                .context("Failed to create solid color brush")
+        }
+    }
+
+    /// Encode the bitmap as a PNG file
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        write_png(
+            &self.wic_factory,
+            &self.bitmap,
+            self.width,
+            self.height,
+            path,
+        )
+    }
+
+    /// Encode the bitmap as PNG in memory
+    pub fn png_bytes(&self) -> Result<Vec<u8>> {
+        unsafe {
+            let stream = CreateStreamOnHGlobal(HGLOBAL::default(), true)
+                .context("Failed to create memory stream")?;
+            encode_png(
+                &self.wic_factory,
+                &self.bitmap,
+                self.width,
+                self.height,
+                &stream,
+            )?;
+
+            let mut stat = STATSTG::default();
+            stream.Stat(&mut stat, STATFLAG_NONAME)?;
+            let mut bytes = vec![0u8; stat.cbSize as usize];
+            let mut read = 0;
+            stream.Seek(0, STREAM_SEEK_SET, None)?;
+            stream
+                .Read(
+                    bytes.as_mut_ptr().cast(),
+                    bytes.len() as u32,
+                    Some(&mut read),
+                )
+                .ok()
+                .context("Failed to read PNG from memory stream")?;
+            bytes.truncate(read as usize);
+            Ok(bytes)
+        }
+    }
+}
+
+/// Create a WIC imaging factory, initializing COM on this thread if needed
+pub fn create_wic_factory() -> Result<IWICImagingFactory> {
+    unsafe {
+        // S_FALSE (already initialized) and an apartment initialized differently before are fine
+        let result = CoInitializeEx(None, COINIT_MULTITHREADED);
+        if result != RPC_E_CHANGED_MODE {
+            result.ok().context("Failed to initialize COM")?;
+        }
+        CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER)
+            .context("Failed to create WIC imaging factory")
+    }
+}
+
+/// Encode a `width`x`height` WIC bitmap as a PNG file
+pub fn write_png(
+    wic_factory: &IWICImagingFactory,
+    source: &IWICBitmap,
+    width: u32,
+    height: u32,
+    path: &Path,
+) -> Result<()> {
+    unsafe {
+        let stream = wic_factory
+            .CreateStream()
+            .context("Failed to create WIC stream")?;
+        stream
+            .InitializeFromFilename(&HSTRING::from(path), GENERIC_WRITE.0)
+            .with_context(|| format!("Failed to open {} for writing", path.display()))?;
+        encode_png(wic_factory, source, width, height, &stream)
+    }
+}
+
+/// Encode a `width`x`height` WIC bitmap as PNG into `stream`
+fn encode_png(
+    wic_factory: &IWICImagingFactory,
+    source: &IWICBitmap,
+    width: u32,
+    height: u32,
+    stream: &IStream,
+) -> Result<()> {
+    unsafe {
+        let encoder = wic_factory
+            .CreateEncoder(&GUID_ContainerFormatPng, std::ptr::null())
+            .context("Failed to create PNG encoder")?;
+        encoder
+            .Initialize(stream, WICBitmapEncoderNoCache)
+            .context("Failed to initialize PNG encoder")?;
+
+        let mut frame = None;
+        encoder
+            .CreateNewFrame(&mut frame, std::ptr::null_mut())
+            .context("Failed to create PNG frame")?;
+        let frame = frame.context("PNG encoder returned no frame")?;
+        frame
+            .Initialize(None)
+            .context("Failed to initialize PNG frame")?;
+        frame.SetSize(width, height)?;
+        frame
+            .WriteSource(source, std::ptr::null())
+            .context("Failed to write PNG frame")?;
+        frame.Commit().context("Failed to commit PNG frame")?;
+        encoder.Commit().context("Failed to commit PNG file")?;
+    }
+    Ok(())
+}
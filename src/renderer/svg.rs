@@ -0,0 +1,235 @@
+use anyhow::Result;
+use std::io::Write;
+use windows::Win32::Graphics::Direct2D::Common::D2D1_COLOR_F;
+use windows_numerics::Vector2;
+
+use crate::renderer::draw_operation::DrawOperation;
+
+/// Write `operations` as an SVG image of `width`x`height` pixels over a black background
+///
+/// Every operation becomes one element in drawing order, with the same coordinates as on
+/// screen. Text is left out, its format can't be read back from DirectWrite.
+pub fn write_svg<'a>(
+    writer: &mut impl Write,
+    width: u32,
+    height: u32,
+    operations: impl IntoIterator<Item = &'a DrawOperation>,
+) -> Result<()> {
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )?;
+    writeln!(
+        writer,
+        r##"<rect width="{width}" height="{height}" fill="#000000"/>"##
+    )?;
+    for (index, operation) in operations.into_iter().enumerate() {
+        write_operation(writer, index, operation)?;
+    }
+    writeln!(writer, "</svg>")?;
+    Ok(())
+}
+
+fn write_operation(writer: &mut impl Write, index: usize, operation: &DrawOperation) -> Result<()> {
+    match operation {
+        DrawOperation::Line {
+            start,
+            end,
+            color,
+            thickness,
+        } => writeln!(
+            writer,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+            start.X,
+            start.Y,
+            end.X,
+            end.Y,
+            stroke(color, *thickness)
+        )?,
+        DrawOperation::Rect {
+            rect,
+            color,
+            thickness,
+        } => writeln!(
+            writer,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" {}/>"#,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            stroke(color, *thickness)
+        )?,
+        DrawOperation::FilledRect { rect, color } => writeln!(
+            writer,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            fill(color)
+        )?,
+        DrawOperation::FilledRoundedRect {
+            rect,
+            radius_x,
+            radius_y,
+            color,
+        } => writeln!(
+            writer,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" ry="{}" {}/>"#,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            radius_x,
+            radius_y,
+            fill(color)
+        )?,
+        DrawOperation::Polyline {
+            points,
+            color,
+            thickness,
+        } => {
+            let points: Vec<String> = points
+                .iter()
+                .map(|point| format!("{},{}", point.X, point.Y))
+                .collect();
+            writeln!(
+                writer,
+                r#"<polyline points="{}" fill="none" {}/>"#,
+                points.join(" "),
+                stroke(color, *thickness)
+            )?;
+        }
+        DrawOperation::BezierPath {
+            points,
+            color,
+            thickness,
+        } if points.len() >= 2 => writeln!(
+            writer,
+            r#"<path d="{}" fill="none" {}/>"#,
+            bezier_path_data(points),
+            stroke(color, *thickness)
+        )?,
+        DrawOperation::BezierPath { .. } => {}
+        DrawOperation::GradientRect {
+            rect,
+            stops,
+            start,
+            end,
+        } => {
+            // Gradient in target coordinates, like the Direct2D brush
+            writeln!(
+                writer,
+                r#"<defs><linearGradient id="gradient{index}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}">"#,
+                start.X, start.Y, end.X, end.Y
+            )?;
+            for (position, color) in stops {
+                writeln!(
+                    writer,
+                    r#"<stop offset="{position}" stop-color="{}" stop-opacity="{}"/>"#,
+                    hex(color),
+                    color.a
+                )?;
+            }
+            writeln!(writer, "</linearGradient></defs>")?;
+            writeln!(
+                writer,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="url(#gradient{index})"/>"#,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top
+            )?;
+        }
+        DrawOperation::Ellipse {
+            center,
+            radius_x,
+            radius_y,
+            color,
+            filled,
+            thickness,
+        } => {
+            let paint = if *filled {
+                fill(color)
+            } else {
+                format!(r#"fill="none" {}"#, stroke(color, *thickness))
+            };
+            writeln!(
+                writer,
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {paint}/>"#,
+                center.X, center.Y, radius_x, radius_y
+            )?;
+        }
+        DrawOperation::Arc {
+            center,
+            radius,
+            start_angle,
+            sweep_angle,
+            color,
+            thickness,
+        } => writeln!(
+            writer,
+            r#"<path d="{}" fill="none" {}/>"#,
+            arc_path_data(*center, *radius, *start_angle, *sweep_angle),
+            stroke(color, *thickness)
+        )?,
+        DrawOperation::Text { .. } => {}
+    }
+    Ok(())
+}
+
+/// `#rrggbb` of a color, the alpha goes into a separate opacity attribute
+fn hex(color: &D2D1_COLOR_F) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.r),
+        channel(color.g),
+        channel(color.b)
+    )
+}
+
+fn fill(color: &D2D1_COLOR_F) -> String {
+    format!(r#"fill="{}" fill-opacity="{}""#, hex(color), color.a)
+}
+
+/// Flat caps, like the renderer's default stroke style
+fn stroke(color: &D2D1_COLOR_F, thickness: f32) -> String {
+    format!(
+        r#"stroke="{}" stroke-opacity="{}" stroke-width="{thickness}" stroke-linecap="butt""#,
+        hex(color),
+        color.a
+    )
+}
+
+/// Path data of a `DrawOperation::BezierPath`, the same curve as `bezier_path_geometry`
+fn bezier_path_data(points: &[Vector2]) -> String {
+    let midpoint = |a: Vector2, b: Vector2| ((a.X + b.X) / 2.0, (a.Y + b.Y) / 2.0);
+    let (first_x, first_y) = midpoint(points[0], points[1]);
+    let mut data = format!("M {} {} L {first_x} {first_y}", points[0].X, points[0].Y);
+    for window in points.windows(3) {
+        let (end_x, end_y) = midpoint(window[1], window[2]);
+        data += &format!(" Q {} {} {end_x} {end_y}", window[1].X, window[1].Y);
+    }
+    let last = points[points.len() - 1];
+    data += &format!(" L {} {}", last.X, last.Y);
+    data
+}
+
+/// Path data of a `DrawOperation::Arc`, split in two halves like `arc_geometry`
+fn arc_path_data(center: Vector2, radius: f32, start_angle: f32, sweep_angle: f32) -> String {
+    let sweep_angle = sweep_angle.clamp(-360.0, 360.0);
+    let point_at = |angle: f32| {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        (center.X + radius * cos, center.Y + radius * sin)
+    };
+    // SVG's sweep flag 1 is the positive angle direction, clockwise on screen
+    let sweep_flag = u8::from(sweep_angle >= 0.0);
+    let (start_x, start_y) = point_at(start_angle);
+    let mut data = format!("M {start_x} {start_y}");
+    for half in 1..=2 {
+        let (x, y) = point_at(start_angle + sweep_angle * half as f32 / 2.0);
+        data += &format!(" A {radius} {radius} 0 0 {sweep_flag} {x} {y}");
+    }
+    data
+}
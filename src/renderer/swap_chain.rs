@@ -0,0 +1,85 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use windows::Win32::Graphics::Dxgi::{
+    DXGI_SWAP_EFFECT, DXGI_SWAP_EFFECT_FLIP_DISCARD, DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL,
+};
+
+/// Smallest buffer count allowed for flip model swap chains
+const MIN_BUFFER_COUNT: u32 = 2;
+/// Largest buffer count allowed by DXGI
+const MAX_BUFFER_COUNT: u32 = 16;
+
+/// Presentation model of the swap chain
+///
+/// Only the flip models are offered: swap chains created with `CreateSwapChainForComposition`
+/// must use one of them, the legacy blt models (DISCARD/SEQUENTIAL) fail to create.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwapEffect {
+    /// Back buffer contents are undefined after Present (lowest overhead)
+    #[default]
+    FlipDiscard,
+    /// Back buffer contents are preserved after Present
+    FlipSequential,
+}
+
+impl From<SwapEffect> for DXGI_SWAP_EFFECT {
+    fn from(effect: SwapEffect) -> Self {
+        match effect {
+            SwapEffect::FlipDiscard => DXGI_SWAP_EFFECT_FLIP_DISCARD,
+            SwapEffect::FlipSequential => DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL,
+        }
+    }
+}
+
+/// Swap chain tuning for presentation latency/smoothness
+///
+/// Any combination of a flip swap effect with 2..=16 buffers works with composition swap chains.
+/// 2 buffers (double buffering) gives the lowest latency, 3 (triple buffering) can smooth out
+/// presentation on GPUs that occasionally miss a vsync.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwapChainConfig {
+    pub buffer_count: u32,
+    pub swap_effect: SwapEffect,
+    /// Clear and present every back buffer once right after creation, so the compositor
+    /// never picks up an uninitialized buffer while the first frame is being prepared
+    pub pre_clear: bool,
+    /// Draw the scene at twice the resolution (4 samples per pixel) and filter it down before
+    /// presenting, which smooths diagonal and curved roads. Direct2D can't draw into
+    /// multisampled targets, so this supersamples instead; it costs four times the fill rate
+    /// and memory of the scene bitmap. Falls back to native resolution on devices that can't
+    /// hold a bitmap that large.
+    pub supersampling: bool,
+    /// Create the swap chain with a frame latency waitable object and wait on it before every
+    /// frame, so frames start in step with the display instead of drifting against the frame
+    /// timer. Keeps at most one frame queued. Only takes effect with vsync.
+    pub frame_pacing: bool,
+}
+
+impl Default for SwapChainConfig {
+    fn default() -> Self {
+        Self {
+            buffer_count: 2,
+            swap_effect: SwapEffect::FlipDiscard,
+            pre_clear: true,
+            supersampling: false,
+            frame_pacing: false,
+        }
+    }
+}
+
+impl SwapChainConfig {
+    /// Check that the combination is accepted by `CreateSwapChainForComposition`
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_BUFFER_COUNT..=MAX_BUFFER_COUNT).contains(&self.buffer_count) {
+            bail!(
+                "Swap chain buffer_count must be between {} and {} for {:?}, got {}",
+                MIN_BUFFER_COUNT,
+                MAX_BUFFER_COUNT,
+                self.swap_effect,
+                self.buffer_count
+            );
+        }
+        Ok(())
+    }
+}
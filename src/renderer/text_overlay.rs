@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use windows::Win32::Graphics::{
+    Direct2D::Common::{D2D_RECT_F, D2D1_COLOR_F},
+    DirectWrite::{
+        DWRITE_PARAGRAPH_ALIGNMENT, DWRITE_PARAGRAPH_ALIGNMENT_CENTER,
+        DWRITE_PARAGRAPH_ALIGNMENT_FAR, DWRITE_PARAGRAPH_ALIGNMENT_NEAR, DWRITE_TEXT_ALIGNMENT,
+        DWRITE_TEXT_ALIGNMENT_CENTER, DWRITE_TEXT_ALIGNMENT_LEADING,
+        DWRITE_TEXT_ALIGNMENT_TRAILING, IDWriteTextFormat,
+    },
+};
+
+/// Where on the screen a text overlay is placed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextAnchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl TextAnchor {
+    /// Align text inside its layout rect so it sticks to this anchor
+    pub fn apply(self, format: &IDWriteTextFormat) {
+        let (text_alignment, paragraph_alignment): (
+            DWRITE_TEXT_ALIGNMENT,
+            DWRITE_PARAGRAPH_ALIGNMENT,
+        ) = match self {
+            Self::TopLeft => (
+                DWRITE_TEXT_ALIGNMENT_LEADING,
+                DWRITE_PARAGRAPH_ALIGNMENT_NEAR,
+            ),
+            Self::TopRight => (
+                DWRITE_TEXT_ALIGNMENT_TRAILING,
+                DWRITE_PARAGRAPH_ALIGNMENT_NEAR,
+            ),
+            Self::BottomLeft => (
+                DWRITE_TEXT_ALIGNMENT_LEADING,
+                DWRITE_PARAGRAPH_ALIGNMENT_FAR,
+            ),
+            Self::BottomRight => (
+                DWRITE_TEXT_ALIGNMENT_TRAILING,
+                DWRITE_PARAGRAPH_ALIGNMENT_FAR,
+            ),
+            Self::Center => (
+                DWRITE_TEXT_ALIGNMENT_CENTER,
+                DWRITE_PARAGRAPH_ALIGNMENT_CENTER,
+            ),
+        };
+        unsafe {
+            let _ = format.SetTextAlignment(text_alignment);
+            let _ = format.SetParagraphAlignment(paragraph_alignment);
+        }
+    }
+}
+
+/// Text drawn on top of every presented frame, outside of the accumulated scene
+#[derive(Clone, PartialEq)]
+pub struct TextOverlay {
+    pub text: String,
+    pub format: IDWriteTextFormat,
+    pub rect: D2D_RECT_F,
+    pub color: D2D1_COLOR_F,
+}
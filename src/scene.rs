@@ -1,5 +1,34 @@
+use crate::config::CityGrowConfig;
 use crate::renderer::Renderer;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// Highest quality level passed to `Scene::set_quality_level`
+pub const MAX_QUALITY_LEVEL: u8 = 2;
+
+/// Step of the animation cycle, reported through `Scene::take_phase_changes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationPhase {
+    /// A new city started growing
+    Growing,
+    /// Growth finished, the complete city is shown
+    Holding,
+    /// The city started unwinding
+    Reversing,
+    /// The city is fully gone, a new one starts
+    Restarting,
+}
+
+/// Scenes that can be selected by name in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SceneId {
+    /// The growing city
+    CityGrow,
+    /// Static test pattern, same as `--selftest`
+    SelfTest,
+}
 
 /// Trait for scene rendering logic (the "frontend")
 pub trait Scene {
@@ -12,6 +41,130 @@ pub trait Scene {
     /// Handle resize events
     fn on_resize(&mut self, width: u32, height: u32);
 
+    /// Called after the renderer was recreated (e.g. after a GPU device loss), everything
+    /// drawn so far is gone and the scene has to start over
+    fn on_renderer_recreated(&mut self, width: u32, height: u32) {
+        self.on_resize(width, height);
+    }
+
+    /// Called with the DPI of the monitor the scene is shown on, once the renderer exists
+    /// and whenever it changes. Everything is drawn in physical pixels (1 DIP = 1 pixel).
+    fn on_dpi_changed(&mut self, _dpi: u32) {}
+
     /// Check if the scene is currently animating and needs rendering
     fn is_animating(&self) -> bool;
+
+    /// The scene has nothing more to show and the app should exit
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    /// While not animating, how long until the scene animates again on its own
+    /// (None = only after an outside change such as a resize)
+    fn wake_after(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Short human-readable summary of the scene state, used for debug logging
+    fn status(&self) -> String {
+        String::new()
+    }
+
+    /// Reduce (or restore) optional per-frame work, 0 is the cheapest and
+    /// `MAX_QUALITY_LEVEL` renders everything as configured
+    fn set_quality_level(&mut self, _level: u8) {}
+
+    /// Freeze the animation where it is, the renderer stays alive
+    fn pause(&mut self) {}
+
+    /// Continue a paused animation from where it stopped
+    fn resume(&mut self) {}
+
+    /// Phase transitions since the last call, oldest first. Scenes without a cycle report none.
+    fn take_phase_changes(&mut self) -> Vec<AnimationPhase> {
+        Vec::new()
+    }
+
+    /// Write the scene state to `path` so it can continue after a restart, called on exit.
+    /// Scenes without state worth keeping write nothing.
+    fn save_state(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Continue from the state `save_state` wrote to `path`, for when the file is only known
+    /// after the scene was created. Scenes without saved state stay as they are.
+    fn restore_state(&mut self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Take over a configuration that was edited while running. Settings that only affect
+    /// what is drawn next apply immediately, ones that change the layout start a new city.
+    fn apply_config(&mut self, _config: &CityGrowConfig) {}
+}
+
+/// Lets `App` run a scene chosen at runtime (`App<Box<dyn Scene>>`)
+impl<T: Scene + ?Sized> Scene for Box<T> {
+    fn prepare_render(&mut self, renderer: &mut Renderer) -> Result<()> {
+        (**self).prepare_render(renderer)
+    }
+
+    fn render(&mut self, renderer: &mut Renderer, delta_time: f32) -> Result<()> {
+        (**self).render(renderer, delta_time)
+    }
+
+    fn on_resize(&mut self, width: u32, height: u32) {
+        (**self).on_resize(width, height)
+    }
+
+    fn on_renderer_recreated(&mut self, width: u32, height: u32) {
+        (**self).on_renderer_recreated(width, height)
+    }
+
+    fn on_dpi_changed(&mut self, dpi: u32) {
+        (**self).on_dpi_changed(dpi)
+    }
+
+    fn is_animating(&self) -> bool {
+        (**self).is_animating()
+    }
+
+    fn is_finished(&self) -> bool {
+        (**self).is_finished()
+    }
+
+    fn wake_after(&self) -> Option<Duration> {
+        (**self).wake_after()
+    }
+
+    fn status(&self) -> String {
+        (**self).status()
+    }
+
+    fn set_quality_level(&mut self, level: u8) {
+        (**self).set_quality_level(level)
+    }
+
+    fn pause(&mut self) {
+        (**self).pause()
+    }
+
+    fn resume(&mut self) {
+        (**self).resume()
+    }
+
+    fn take_phase_changes(&mut self) -> Vec<AnimationPhase> {
+        (**self).take_phase_changes()
+    }
+
+    fn save_state(&self, path: &Path) -> Result<()> {
+        (**self).save_state(path)
+    }
+
+    fn restore_state(&mut self, path: &Path) -> Result<()> {
+        (**self).restore_state(path)
+    }
+
+    fn apply_config(&mut self, config: &CityGrowConfig) {
+        (**self).apply_config(config)
+    }
 }
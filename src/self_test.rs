@@ -0,0 +1,171 @@
+use crate::{
+    ext::color_ext::D2DColorExt,
+    renderer::{Renderer, draw_operation::DrawOperation},
+    scene::Scene,
+};
+use anyhow::Result;
+use tracing::info;
+use windows::Win32::Graphics::{
+    Direct2D::Common::{D2D_RECT_F, D2D1_COLOR_F},
+    DirectWrite::IDWriteTextFormat,
+};
+use windows_numerics::Vector2;
+
+const fn opaque(r: f32, g: f32, b: f32) -> D2D1_COLOR_F {
+    D2D1_COLOR_F { r, g, b, a: 1.0 }
+}
+
+const COLOR_BARS: [D2D1_COLOR_F; 8] = [
+    opaque(1.0, 1.0, 1.0), // White
+    opaque(1.0, 1.0, 0.0), // Yellow
+    opaque(0.0, 1.0, 1.0), // Cyan
+    opaque(0.0, 1.0, 0.0), // Green
+    opaque(1.0, 0.0, 1.0), // Magenta
+    opaque(1.0, 0.0, 0.0), // Red
+    opaque(0.0, 0.0, 1.0), // Blue
+    opaque(0.0, 0.0, 0.0), // Black
+];
+
+const CROSSHAIR_COLOR: D2D1_COLOR_F = opaque(1.0, 1.0, 1.0);
+/// Space between the info text and the edge of its background panel
+const PANEL_PADDING: f32 = 16.0;
+
+/// Diagnostic scene that draws a static test pattern through the real renderer pipeline
+///
+/// If this pattern shows up but the city doesn't, the problem is in the scene.
+/// If the screen stays black, the problem is in the device/compositor setup.
+pub struct SelfTestScene {
+    text_format: Option<IDWriteTextFormat>,
+    rendered: bool,
+}
+
+impl SelfTestScene {
+    pub fn new() -> Self {
+        Self {
+            text_format: None,
+            rendered: false,
+        }
+    }
+
+    /// Vertical color bars filling the whole target
+    fn color_bars(width: f32, height: f32) -> Vec<DrawOperation> {
+        let bar_width = width / COLOR_BARS.len() as f32;
+        COLOR_BARS
+            .iter()
+            .enumerate()
+            .map(|(i, color)| {
+                let rect = D2D_RECT_F {
+                    left: i as f32 * bar_width,
+                    top: 0.0,
+                    right: (i + 1) as f32 * bar_width,
+                    bottom: height,
+                };
+                DrawOperation::filled_rect(rect, *color)
+            })
+            .collect()
+    }
+
+    /// Crosshair through the center of the target, with a box marking the center
+    fn crosshair(width: f32, height: f32) -> Vec<DrawOperation> {
+        let center = Vector2 {
+            X: width / 2.0,
+            Y: height / 2.0,
+        };
+        let box_size = width.min(height) / 10.0;
+        vec![
+            DrawOperation::line(
+                Vector2 {
+                    X: 0.0,
+                    Y: center.Y,
+                },
+                Vector2 {
+                    X: width,
+                    Y: center.Y,
+                },
+                CROSSHAIR_COLOR,
+                2.0,
+            ),
+            DrawOperation::line(
+                Vector2 {
+                    X: center.X,
+                    Y: 0.0,
+                },
+                Vector2 {
+                    X: center.X,
+                    Y: height,
+                },
+                CROSSHAIR_COLOR,
+                2.0,
+            ),
+            DrawOperation::rect(
+                D2D_RECT_F {
+                    left: center.X - box_size,
+                    top: center.Y - box_size,
+                    right: center.X + box_size,
+                    bottom: center.Y + box_size,
+                },
+                CROSSHAIR_COLOR,
+                2.0,
+            ),
+        ]
+    }
+}
+
+impl Scene for SelfTestScene {
+    /// Animate only until the pattern has been drawn once, then hold it
+    fn is_animating(&self) -> bool {
+        !self.rendered
+    }
+
+    fn prepare_render(&mut self, renderer: &mut Renderer) -> Result<()> {
+        if self.text_format.is_none() {
+            self.text_format = Some(renderer.create_text_format("Consolas", 28.0)?);
+        }
+        Ok(())
+    }
+
+    fn render(&mut self, renderer: &mut Renderer, _delta_time: f32) -> Result<()> {
+        let (width, height) = renderer.size();
+        let (width, height) = (width as f32, height as f32);
+
+        renderer.clear(D2D1_COLOR_F::black());
+        renderer.draw_batch(&Self::color_bars(width, height))?;
+        renderer.draw_batch(&Self::crosshair(width, height))?;
+
+        let info_text = format!(
+            "City Grow self-test\n{}x{} @ {} DPI\nGPU: {}",
+            width,
+            height,
+            renderer.dpi(),
+            renderer.adapter_name()
+        );
+        if let Some(text_format) = &self.text_format {
+            // Panel sized to the text, centered in the lower part of the screen
+            let (text_width, text_height) =
+                renderer.measure_text(&info_text, text_format, width * 0.8)?;
+            let panel_width = text_width + 2.0 * PANEL_PADDING;
+            let panel_height = text_height + 2.0 * PANEL_PADDING;
+            let panel = D2D_RECT_F {
+                left: (width - panel_width) / 2.0,
+                top: height * 0.75 - panel_height / 2.0,
+                right: (width + panel_width) / 2.0,
+                bottom: height * 0.75 + panel_height / 2.0,
+            };
+            renderer.draw_filled_rect(&panel, &D2D1_COLOR_F::black().with_alpha(0.8))?;
+            renderer.draw_text(&info_text, text_format, &panel, &CROSSHAIR_COLOR)?;
+        }
+
+        if !self.rendered {
+            info!(
+                "Self-test pattern rendered: {}",
+                info_text.replace('\n', " | ")
+            );
+            self.rendered = true;
+        }
+        Ok(())
+    }
+
+    fn on_resize(&mut self, _width: u32, _height: u32) {
+        self.rendered = false;
+    }
+}
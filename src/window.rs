@@ -3,7 +3,7 @@ use derive_builder::Builder;
 use windows::Win32::UI::WindowsAndMessaging::GetClientRect;
 use windows::{
     Win32::{
-        Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+        Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM},
         Graphics::Gdi::ValidateRect,
         System::LibraryLoader::GetModuleHandleW,
         UI::WindowsAndMessaging::*,
@@ -16,6 +16,11 @@ const DEFAULT_TIMER_ID: usize = 1;
 const DEFAULT_WINDOW_WIDTH: u32 = 1280;
 const DEFAULT_WINDOW_HEIGHT: u32 = 720;
 
+/// Posted to the window to freeze the animation (e.g. by the host while the screen is locked)
+pub const WM_APP_PAUSE: u32 = WM_APP + 1;
+/// Posted to the window to continue the animation after `WM_APP_PAUSE`
+pub const WM_APP_RESUME: u32 = WM_APP + 2;
+
 /// Extract low-order word from LPARAM
 #[inline]
 const fn loword(lparam: LPARAM) -> u16 {
@@ -40,6 +45,21 @@ pub struct WindowConfig {
     pub height: Option<u32>,
     #[builder(default = 60)]
     pub target_framerate: u32,
+    /// Cover the bounding box of all monitors instead of waiting for the host to place the
+    /// window (fullscreen only)
+    #[builder(default = false)]
+    pub span_virtual_desktop: bool,
+    /// Off runs the message loop without waiting, ticking the timer whenever the queue is
+    /// empty, so frames render as fast as possible (keeps a CPU core busy, for benchmarking)
+    #[builder(default = true)]
+    pub vsync: bool,
+    /// Let mouse input pass through to the windows below (for running without Lively)
+    #[builder(default = false)]
+    pub click_through: bool,
+    /// Keep the window below all other windows and never activate it, like a desktop
+    /// wallpaper (for running without Lively)
+    #[builder(default = false)]
+    pub bottom_most: bool,
 }
 
 /// Trait for handling window events
@@ -55,6 +75,21 @@ pub trait WindowHandler {
 
     /// Called when window is being destroyed
     fn on_destroy(&mut self);
+
+    /// Called when a key is pressed while the window has focus
+    fn on_key(&mut self, _hwnd: HWND, _virtual_key: u32) {}
+
+    /// Called when the window moved to a monitor with a different DPI
+    fn on_dpi_changed(&mut self, _hwnd: HWND, _dpi: u32) {}
+
+    /// Called when the display resolution changed or a monitor was added or removed
+    fn on_display_change(&mut self, _hwnd: HWND) {}
+
+    /// Called when `WM_APP_PAUSE` is received
+    fn on_pause(&mut self) {}
+
+    /// Called when `WM_APP_RESUME` is received
+    fn on_resume(&mut self) {}
 }
 
 /// Handle WM_PAINT message
@@ -80,6 +115,58 @@ fn handle_size<H: WindowHandler>(handler: &mut H, hwnd: HWND, lparam: LPARAM) ->
     LRESULT(0)
 }
 
+/// Handle WM_DPICHANGED message
+fn handle_dpi_changed<H: WindowHandler>(
+    handler: &mut H,
+    hwnd: HWND,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    // Move to the rect Windows suggests for the new DPI, this sends WM_SIZE if it changed
+    let suggested = unsafe { &*(lparam.0 as *const RECT) };
+    unsafe {
+        let _ = SetWindowPos(
+            hwnd,
+            None,
+            suggested.left,
+            suggested.top,
+            suggested.right - suggested.left,
+            suggested.bottom - suggested.top,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+    // X and Y DPI are always equal, X is in the low word
+    handler.on_dpi_changed(hwnd, (wparam.0 & 0xFFFF) as u32);
+    LRESULT(0)
+}
+
+/// Handle WM_DISPLAYCHANGE message
+fn handle_display_change<H: WindowHandler>(handler: &mut H, hwnd: HWND) -> LRESULT {
+    handler.on_display_change(hwnd);
+    LRESULT(0)
+}
+
+/// Handle WM_WINDOWPOSCHANGING message
+///
+/// Bottom-most windows are marked with WS_EX_NOACTIVATE, any z-order change moves them back
+/// to the bottom.
+fn handle_window_pos_changing(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe {
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
+        let window_pos = &mut *(lparam.0 as *mut WINDOWPOS);
+        if ex_style & WS_EX_NOACTIVATE.0 != 0 && (window_pos.flags & SWP_NOZORDER).0 == 0 {
+            window_pos.hwndInsertAfter = HWND_BOTTOM;
+        }
+        DefWindowProcW(hwnd, WM_WINDOWPOSCHANGING, wparam, lparam)
+    }
+}
+
+/// Handle WM_KEYDOWN message
+fn handle_key<H: WindowHandler>(handler: &mut H, hwnd: HWND, wparam: WPARAM) -> LRESULT {
+    handler.on_key(hwnd, wparam.0 as u32);
+    LRESULT(0)
+}
+
 /// Handle WM_DESTROY message
 fn handle_destroy<H: WindowHandler>(handler: &mut H, handler_ptr: *mut H) -> LRESULT {
     handler.on_destroy();
@@ -101,6 +188,7 @@ fn handle_close(hwnd: HWND) -> LRESULT {
 #[allow(dead_code)]
 pub struct Window {
     hwnd: HWND,
+    vsync: bool,
 }
 
 #[allow(dead_code)]
@@ -123,29 +211,51 @@ impl Window {
             RegisterClassW(&wc); // Ignore error if already registered
 
             // Determine window style and dimensions based on config
-            let (style, ex_style, width, height, x, y) = if config.fullscreen {
-                // For fullscreen/Lively mode, let Lively resize the window
-                // Initially hidden to avoid white flash, shown after first resize
-                let w = config.width.unwrap_or(DEFAULT_WINDOW_WIDTH) as i32;
-                let h = config.height.unwrap_or(DEFAULT_WINDOW_HEIGHT) as i32;
-                (
-                    WS_POPUP,         // No WS_VISIBLE - initially hidden
-                    WS_EX_TOOLWINDOW, // Don't show in taskbar
-                    w,
-                    h,
-                    0,
-                    0,
-                )
-            } else {
-                (
-                    WS_OVERLAPPEDWINDOW,
-                    WINDOW_EX_STYLE::default(),
-                    config.width.unwrap_or(DEFAULT_WINDOW_WIDTH) as i32,
-                    config.height.unwrap_or(DEFAULT_WINDOW_HEIGHT) as i32,
-                    CW_USEDEFAULT,
-                    CW_USEDEFAULT,
-                )
-            };
+            let (style, ex_style, width, height, x, y) =
+                if config.fullscreen && config.span_virtual_desktop {
+                    // Origin of the virtual desktop is the top-left of the leftmost/topmost
+                    // monitor, which is negative when it lies left of/above the primary one
+                    (
+                        WS_POPUP,
+                        WS_EX_TOOLWINDOW,
+                        GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                        GetSystemMetrics(SM_CYVIRTUALSCREEN),
+                        GetSystemMetrics(SM_XVIRTUALSCREEN),
+                        GetSystemMetrics(SM_YVIRTUALSCREEN),
+                    )
+                } else if config.fullscreen {
+                    // For fullscreen/Lively mode, let Lively resize the window
+                    // Initially hidden to avoid white flash, shown after first resize
+                    let w = config.width.unwrap_or(DEFAULT_WINDOW_WIDTH) as i32;
+                    let h = config.height.unwrap_or(DEFAULT_WINDOW_HEIGHT) as i32;
+                    (
+                        WS_POPUP,         // No WS_VISIBLE - initially hidden
+                        WS_EX_TOOLWINDOW, // Don't show in taskbar
+                        w,
+                        h,
+                        0,
+                        0,
+                    )
+                } else {
+                    (
+                        WS_OVERLAPPEDWINDOW,
+                        WINDOW_EX_STYLE::default(),
+                        config.width.unwrap_or(DEFAULT_WINDOW_WIDTH) as i32,
+                        config.height.unwrap_or(DEFAULT_WINDOW_HEIGHT) as i32,
+                        CW_USEDEFAULT,
+                        CW_USEDEFAULT,
+                    )
+                };
+
+            let mut ex_style = ex_style;
+            if config.click_through {
+                // Layered windows are hit-tested per pixel, transparent ones let every click
+                // through
+                ex_style |= WS_EX_LAYERED | WS_EX_TRANSPARENT;
+            }
+            if config.bottom_most {
+                ex_style |= WS_EX_NOACTIVATE;
+            }
 
             // Box the handler on the heap to pass through lpParam
             let handler_ptr = Box::into_raw(Box::new(handler));
@@ -172,6 +282,23 @@ impl Window {
             )
             .context("Failed to create window")?;
 
+            if config.click_through {
+                // Fully opaque, the layered style is only there for the click-through
+                SetLayeredWindowAttributes(hwnd, COLORREF(0), 255, LWA_ALPHA)
+                    .context("Failed to set layered window attributes")?;
+            }
+            if config.bottom_most {
+                let _ = SetWindowPos(
+                    hwnd,
+                    Some(HWND_BOTTOM),
+                    0,
+                    0,
+                    0,
+                    0,
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+                );
+            }
+
             // For non-fullscreen mode, show window immediately
             if !config.fullscreen {
                 let _ = ShowWindow(hwnd, SW_SHOW);
@@ -185,9 +312,9 @@ impl Window {
                 None,
             );
 
-            // Trigger initial resize for non-fullscreen mode
+            // Trigger initial resize for non-fullscreen and spanning mode
             // For fullscreen/Lively mode, wait for Lively to resize the window
-            if !config.fullscreen {
+            if !config.fullscreen || config.span_virtual_desktop {
                 let mut rect = windows::Win32::Foundation::RECT::default();
                 if GetClientRect(hwnd, &mut rect).is_ok() {
                     let actual_width = (rect.right - rect.left) as u32;
@@ -202,7 +329,10 @@ impl Window {
                 }
             }
 
-            Ok(Self { hwnd })
+            Ok(Self {
+                hwnd,
+                vsync: config.vsync,
+            })
         }
     }
 
@@ -211,14 +341,27 @@ impl Window {
     }
 
     /// Run the message loop
-    pub fn run_message_loop() -> Result<()> {
+    pub fn run_message_loop(&self) -> Result<()> {
         unsafe {
             let mut msg = MSG::default();
-            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
-                let _ = TranslateMessage(&msg);
-                DispatchMessageW(&msg);
+            if self.vsync {
+                while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+                return Ok(());
+            }
+
+            loop {
+                while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+                    if msg.message == WM_QUIT {
+                        return Ok(());
+                    }
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+                SendMessageW(self.hwnd, WM_TIMER, Some(WPARAM(DEFAULT_TIMER_ID)), None);
             }
-            Ok(())
         }
     }
 
@@ -257,6 +400,18 @@ impl Window {
             WM_PAINT => handle_paint(handler, hwnd),
             WM_TIMER => handle_timer(handler, hwnd),
             WM_SIZE => handle_size(handler, hwnd, lparam),
+            WM_DPICHANGED => handle_dpi_changed(handler, hwnd, wparam, lparam),
+            WM_DISPLAYCHANGE => handle_display_change(handler, hwnd),
+            WM_KEYDOWN => handle_key(handler, hwnd, wparam),
+            WM_WINDOWPOSCHANGING => handle_window_pos_changing(hwnd, wparam, lparam),
+            WM_APP_PAUSE => {
+                handler.on_pause();
+                LRESULT(0)
+            }
+            WM_APP_RESUME => {
+                handler.on_resume();
+                LRESULT(0)
+            }
             WM_DESTROY => handle_destroy(handler, handler_ptr),
             WM_CLOSE => handle_close(hwnd),
             _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },